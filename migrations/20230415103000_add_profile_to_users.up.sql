@@ -0,0 +1,7 @@
+-- Add profile columns to users
+ALTER TABLE users
+    ADD COLUMN display_name text NULL,
+    ADD COLUMN locale text NULL,
+    ADD COLUMN timezone text NULL,
+    ADD COLUMN bio text NULL,
+    ADD COLUMN avatar_url text NULL;
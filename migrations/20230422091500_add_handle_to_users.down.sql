@@ -0,0 +1,3 @@
+DROP TABLE handle_history;
+DROP INDEX users_handle_lower_idx;
+ALTER TABLE users DROP COLUMN handle;
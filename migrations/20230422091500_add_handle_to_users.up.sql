@@ -0,0 +1,12 @@
+-- Add case-insensitive unique handle to users
+ALTER TABLE users ADD COLUMN handle text NULL;
+CREATE UNIQUE INDEX users_handle_lower_idx ON users (lower(handle));
+
+-- Previous handles, kept to redirect old handles for a grace period
+CREATE TABLE handle_history
+(
+    handle text NOT NULL,
+    user_id uuid NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    changed_at timestamptz NOT NULL DEFAULT NOW()
+);
+CREATE INDEX handle_history_handle_lower_idx ON handle_history (lower(handle));
@@ -0,0 +1,11 @@
+-- Pending email changes waiting for confirmation of the new address
+CREATE TABLE email_change_requests
+(
+    token text NOT NULL,
+    PRIMARY KEY (token),
+    user_id uuid NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    new_email text NOT NULL,
+    created_at timestamptz NOT NULL DEFAULT NOW(),
+    expires_at timestamptz NOT NULL
+);
+CREATE INDEX email_change_requests_user_id_idx ON email_change_requests (user_id);
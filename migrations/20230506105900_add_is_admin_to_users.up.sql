@@ -0,0 +1,2 @@
+-- Privileged users allowed to use the admin endpoints
+ALTER TABLE users ADD COLUMN is_admin boolean NOT NULL DEFAULT false;
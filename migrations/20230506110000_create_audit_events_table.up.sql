@@ -0,0 +1,18 @@
+-- Record of every user mutation
+CREATE TABLE audit_events
+(
+    id uuid NOT NULL,
+    PRIMARY KEY (id),
+    occurred_at timestamptz NOT NULL DEFAULT NOW(),
+    -- NULL when the request was not authenticated
+    actor_id uuid NULL,
+    action text NOT NULL,
+    target_id uuid NOT NULL,
+    -- Names only, values are never recorded
+    changed_fields text[] NOT NULL DEFAULT '{}',
+    request_id text NULL,
+    ip_address text NULL
+);
+CREATE INDEX audit_events_target_id_idx ON audit_events (target_id);
+CREATE INDEX audit_events_actor_id_idx ON audit_events (actor_id);
+CREATE INDEX audit_events_occurred_at_idx ON audit_events (occurred_at);
@@ -20,14 +20,6 @@ then
     exit
 fi
 
-# Check does sqlx command exist
-if ! command -v sqlx &> /dev/null
-then
-    echo "sqlx could not be found, please install sqlx-cli first"
-    echo "you can install it by running: cargo install sqlx-cli"
-    exit
-fi
-
 # Check if a custom user is provided or default to "postgres"
 DB_USER="${POSTGRES_USER:=postgres}"
 
@@ -62,15 +54,18 @@ done
 >&2 echo "Postgres is up and running on port $DB_PORT"
 
 
-# Export the database url for sqlx
+# Export the database url for sqlx and the binary
 DATABASE_URL=postgres://$DB_USER:$DB_PASSWORD@$DB_HOST:$DB_PORT/$DB_NAME
 export DATABASE_URL="${DATABASE_URL}"
 
-# Create the database 
-sqlx database create
+# Create the database and run the migrations embedded in the binary,
+# the compile time checked queries use the offline data in .sqlx
+SQLX_OFFLINE=true cargo run --quiet -- migrate up
 
-# Run the migrations
-sqlx migrate run
+# Add fixture users, e.g. with SEED=true ./scripts/init_db.sh
+if [[ "${SEED}" == "true" ]]; then
+    SQLX_OFFLINE=true cargo run --quiet -- seed
+fi
 
 # Print when the database is ready
 >&2 echo "Finished migrations the database, lets get started!"
\ No newline at end of file
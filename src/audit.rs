@@ -0,0 +1,82 @@
+use actix_web::{dev::Payload, FromRequest, HttpMessage, HttpRequest};
+use futures_util::future::LocalBoxFuture;
+use sqlx::{Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::authentication::{Actor, AuthError};
+use crate::request_id::RequestId;
+
+pub enum AuditAction {
+    UserCreated,
+    UserUpdated,
+    UserDeleted,
+}
+
+impl AuditAction {
+    pub fn as_str(&self) -> &str {
+        match self {
+            AuditAction::UserCreated => "user.created",
+            AuditAction::UserUpdated => "user.updated",
+            AuditAction::UserDeleted => "user.deleted",
+        }
+    }
+}
+
+// Who performed a mutation and from where
+#[derive(Debug, Clone)]
+pub struct AuditContext {
+    pub actor_id: Option<Uuid>,
+    pub request_id: Option<String>,
+    pub ip_address: Option<String>,
+}
+
+impl FromRequest for AuditContext {
+    type Error = AuthError;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let actor = Actor::from_request(req, payload);
+        let request_id = req
+            .extensions()
+            .get::<RequestId>()
+            .map(|request_id| request_id.to_string());
+        // The socket peer, forwarding headers are set by the client and cannot be trusted
+        let ip_address = req.peer_addr().map(|address| address.ip().to_string());
+        Box::pin(async move {
+            let Actor(actor_id) = actor.await?;
+            Ok(AuditContext {
+                actor_id,
+                request_id,
+                ip_address,
+            })
+        })
+    }
+}
+
+// Record an audit event as part of the mutation's transaction
+#[tracing::instrument(name = "Insert Audit Event In Database", skip(transaction, context, action), fields(action = %action.as_str()))]
+pub async fn insert_audit_event(
+    transaction: &mut Transaction<'_, Postgres>,
+    context: &AuditContext,
+    action: AuditAction,
+    target_id: Uuid,
+    changed_fields: &[&str],
+) -> Result<(), sqlx::Error> {
+    let changed_fields: Vec<String> = changed_fields.iter().map(|f| f.to_string()).collect();
+    sqlx::query!(
+        r#"
+        INSERT INTO audit_events (id, actor_id, action, target_id, changed_fields, request_id, ip_address)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        "#,
+        Uuid::new_v4(),
+        context.actor_id,
+        action.as_str(),
+        target_id,
+        &changed_fields,
+        context.request_id,
+        context.ip_address
+    )
+    .execute(&mut **transaction)
+    .await?;
+    Ok(())
+}
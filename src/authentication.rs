@@ -0,0 +1,276 @@
+use actix_web::HttpMessage;
+use actix_web::{
+    dev::Payload,
+    http::{header, StatusCode},
+    web, FromRequest, HttpRequest, HttpResponse, ResponseError,
+};
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use base64::Engine;
+use futures_util::future::LocalBoxFuture;
+use once_cell::sync::Lazy;
+use secrecy::{ExposeSecret, Secret};
+use sha2::{Digest, Sha256};
+use std::fmt;
+use uuid::Uuid;
+
+use crate::metrics::ConnectionPool;
+
+pub struct Credentials {
+    pub email: String,
+    pub password: Secret<String>,
+}
+
+#[derive(Debug)]
+pub enum AuthError {
+    // Missing, malformed or wrong credentials
+    InvalidCredentials,
+    // Valid credentials without the required privileges
+    Forbidden,
+    Unexpected(sqlx::Error),
+}
+
+impl fmt::Display for AuthError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuthError::InvalidCredentials => write!(f, "Invalid credentials"),
+            AuthError::Forbidden => write!(f, "Forbidden"),
+            AuthError::Unexpected(_) => write!(f, "Internal Server Error"),
+        }
+    }
+}
+
+impl std::error::Error for AuthError {}
+
+impl ResponseError for AuthError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            AuthError::InvalidCredentials => StatusCode::UNAUTHORIZED,
+            AuthError::Forbidden => StatusCode::FORBIDDEN,
+            AuthError::Unexpected(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let mut response = HttpResponse::build(self.status_code());
+        if let AuthError::InvalidCredentials = self {
+            response.insert_header((header::WWW_AUTHENTICATE, r#"Basic realm="actix-template""#));
+        }
+        response.body(self.to_string())
+    }
+}
+
+// Parse `Authorization: Basic` credentials, None when the header is absent
+pub fn basic_authentication(request: &HttpRequest) -> Result<Option<Credentials>, AuthError> {
+    let header_value = match request.headers().get(header::AUTHORIZATION) {
+        Some(header_value) => header_value,
+        None => return Ok(None),
+    };
+    let encoded = header_value
+        .to_str()
+        .ok()
+        .and_then(|value| value.strip_prefix("Basic "))
+        .ok_or(AuthError::InvalidCredentials)?;
+    let decoded = base64::engine::general_purpose::STANDARD
+        .decode(encoded)
+        .map_err(|_| AuthError::InvalidCredentials)?;
+    let decoded = String::from_utf8(decoded).map_err(|_| AuthError::InvalidCredentials)?;
+    let (email, password) = decoded
+        .split_once(':')
+        .ok_or(AuthError::InvalidCredentials)?;
+    Ok(Some(Credentials {
+        email: email.into(),
+        password: Secret::new(password.into()),
+    }))
+}
+
+// Verified against when the email is unknown so both cases take as long
+static UNKNOWN_USER_HASH: Lazy<String> = Lazy::new(|| compute_password_hash("unknown-user"));
+
+// Argon2id PHC string with a random salt
+fn compute_password_hash(password: &str) -> String {
+    let salt = SaltString::generate(&mut rand::thread_rng());
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        // Only fails for invalid parameters and the defaults are valid
+        .expect("Failed to hash password.")
+        .to_string()
+}
+
+// Hash a password for storage, off the async runtime
+pub async fn hash_password(password: Secret<String>) -> String {
+    actix_web::rt::task::spawn_blocking(move || compute_password_hash(password.expose_secret()))
+        .await
+        .expect("Password hashing panicked.")
+}
+
+// Check a password against a stored hash, off the async runtime
+pub async fn verify_password(password_hash: String, password: Secret<String>) -> bool {
+    actix_web::rt::task::spawn_blocking(move || {
+        PasswordHash::new(&password_hash).is_ok_and(|password_hash| {
+            Argon2::default()
+                .verify_password(password.expose_secret().as_bytes(), &password_hash)
+                .is_ok()
+        })
+    })
+    .await
+    .unwrap_or(false)
+}
+
+// User whose credentials were verified, required by handlers that act on an account
+#[derive(Debug, Clone, Copy)]
+pub struct AuthenticatedUser {
+    pub id: Uuid,
+    pub is_admin: bool,
+}
+
+impl AuthenticatedUser {
+    // Users manage their own account, admins manage every account
+    pub fn can_manage(&self, user_id: Uuid) -> bool {
+        self.id == user_id || self.is_admin
+    }
+}
+
+impl FromRequest for AuthenticatedUser {
+    type Error = AuthError;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let req = req.clone();
+        Box::pin(async move {
+            authenticate(&req)
+                .await?
+                .ok_or(AuthError::InvalidCredentials)
+        })
+    }
+}
+
+struct StoredCredentials {
+    id: Uuid,
+    is_admin: bool,
+    password: String,
+}
+
+#[tracing::instrument(name = "Validate Credentials", skip(credentials, db_pool), fields(email = %credentials.email))]
+async fn validate_credentials(
+    credentials: Credentials,
+    db_pool: &ConnectionPool,
+) -> Result<AuthenticatedUser, AuthError> {
+    let mut connection = db_pool.acquire().await.map_err(AuthError::Unexpected)?;
+    let stored = sqlx::query_as!(
+        StoredCredentials,
+        r#"
+        SELECT id, is_admin, password FROM users WHERE email = $1
+        "#,
+        credentials.email
+    )
+    .fetch_optional(&mut *connection)
+    .await
+    .map_err(AuthError::Unexpected)?;
+    let Some(stored) = stored else {
+        // Same work as for a known email so the response time does not reveal it
+        verify_password(UNKNOWN_USER_HASH.clone(), credentials.password).await;
+        return Err(AuthError::InvalidCredentials);
+    };
+    if PasswordHash::new(&stored.password).is_err() {
+        // Stored as plaintext before passwords were hashed, upgraded on the first sign in
+        if !matches_plaintext(&stored.password, &credentials.password) {
+            return Err(AuthError::InvalidCredentials);
+        }
+        upgrade_password(stored.id, &stored.password, credentials.password, db_pool)
+            .await
+            .map_err(AuthError::Unexpected)?;
+    } else if !verify_password(stored.password, credentials.password).await {
+        return Err(AuthError::InvalidCredentials);
+    }
+    Ok(AuthenticatedUser {
+        id: stored.id,
+        is_admin: stored.is_admin,
+    })
+}
+
+// Digests are compared so the time taken does not depend on the matching prefix
+fn matches_plaintext(stored: &str, password: &Secret<String>) -> bool {
+    Sha256::digest(stored.as_bytes()) == Sha256::digest(password.expose_secret().as_bytes())
+}
+
+#[tracing::instrument(
+    name = "Upgrade Password Hash In Database",
+    skip(plaintext, password, db_pool)
+)]
+async fn upgrade_password(
+    id: Uuid,
+    plaintext: &str,
+    password: Secret<String>,
+    db_pool: &ConnectionPool,
+) -> Result<(), sqlx::Error> {
+    let password_hash = hash_password(password).await;
+    // Left alone when the password changed since it was read
+    sqlx::query!(
+        r#"
+        UPDATE users SET password = $1 WHERE id = $2 AND password = $3
+        "#,
+        password_hash,
+        id,
+        plaintext
+    )
+    .execute(&mut *db_pool.acquire().await?)
+    .await?;
+    Ok(())
+}
+
+// Verified once per request, later extractors reuse the result
+async fn authenticate(request: &HttpRequest) -> Result<Option<AuthenticatedUser>, AuthError> {
+    if let Some(user) = request.extensions().get::<AuthenticatedUser>() {
+        return Ok(Some(*user));
+    }
+    let credentials = match basic_authentication(request)? {
+        Some(credentials) => credentials,
+        None => return Ok(None),
+    };
+    let db_pool = request
+        .app_data::<web::Data<ConnectionPool>>()
+        .expect("ConnectionPool is not registered as app data.");
+    let user = validate_credentials(credentials, db_pool).await?;
+    request.extensions_mut().insert(user);
+    Ok(Some(user))
+}
+
+// User performing the request, None for anonymous requests
+#[derive(Debug, Clone, Copy)]
+pub struct Actor(pub Option<Uuid>);
+
+impl FromRequest for Actor {
+    type Error = AuthError;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let req = req.clone();
+        Box::pin(async move {
+            let user = authenticate(&req).await?;
+            Ok(Actor(user.map(|user| user.id)))
+        })
+    }
+}
+
+// Authenticated user with admin privileges
+#[derive(Debug, Clone, Copy)]
+pub struct AdminUser {
+    pub id: Uuid,
+}
+
+impl FromRequest for AdminUser {
+    type Error = AuthError;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let req = req.clone();
+        Box::pin(async move {
+            match authenticate(&req).await? {
+                Some(user) if user.is_admin => Ok(AdminUser { id: user.id }),
+                Some(_) => Err(AuthError::Forbidden),
+                None => Err(AuthError::InvalidCredentials),
+            }
+        })
+    }
+}
@@ -0,0 +1,66 @@
+use std::io::{BufRead, Error};
+
+use secrecy::Secret;
+use uuid::Uuid;
+
+use crate::authentication::hash_password;
+use crate::metrics::ConnectionPool;
+
+// Create a privileged user, the password is prompted for unless read from stdin
+pub async fn create_admin(
+    email: &str,
+    name: &str,
+    password_stdin: bool,
+    db_pool: &ConnectionPool,
+) -> Result<Uuid, Error> {
+    if !validator::validate_email(email) {
+        return Err(Error::other(format!(
+            "{} is not a valid email address",
+            email
+        )));
+    }
+    let password = if password_stdin {
+        let mut password = String::new();
+        std::io::stdin().lock().read_line(&mut password)?;
+        password.trim_end_matches(['\r', '\n']).to_string()
+    } else {
+        let password = rpassword::prompt_password("Password: ")?;
+        if rpassword::prompt_password("Confirm password: ")? != password {
+            return Err(Error::other("Passwords do not match"));
+        }
+        password
+    };
+    // Same rule as creating a user through the API
+    if !(8..=255).contains(&password.chars().count()) {
+        return Err(Error::other("Password must be 8 to 255 characters long"));
+    }
+    let password_hash = hash_password(Secret::new(password)).await;
+    insert_admin_repository(email, name, &password_hash, db_pool)
+        .await
+        .map_err(Error::other)?
+        .ok_or_else(|| Error::other(format!("A user with email {} already exists", email)))
+}
+
+#[tracing::instrument(name = "Insert Admin In Database", skip(password_hash, db_pool))]
+async fn insert_admin_repository(
+    email: &str,
+    name: &str,
+    password_hash: &str,
+    db_pool: &ConnectionPool,
+) -> Result<Option<Uuid>, sqlx::Error> {
+    let id = Uuid::new_v4();
+    let result = sqlx::query!(
+        r#"
+        INSERT INTO users (id, name, email, password, is_admin)
+        VALUES ($1, $2, $3, $4, true)
+        ON CONFLICT (email) DO NOTHING
+        "#,
+        id,
+        name,
+        email,
+        password_hash
+    )
+    .execute(&mut *db_pool.acquire().await?)
+    .await?;
+    Ok((result.rows_affected() == 1).then_some(id))
+}
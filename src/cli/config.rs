@@ -0,0 +1,25 @@
+use std::io::Error;
+
+use super::{load_settings, ConfigCommand};
+use crate::configuration::describe;
+
+pub fn run_config_command(
+    command: ConfigCommand,
+    environment: Option<String>,
+) -> Result<(), Error> {
+    let (settings, sources) = load_settings(environment)?;
+    match command {
+        ConfigCommand::Show => {
+            for value in describe(&settings, &sources) {
+                println!("{}", value);
+            }
+        }
+        ConfigCommand::Check => {
+            println!(
+                "Configuration for {} is valid",
+                settings.environment.as_str()
+            );
+        }
+    }
+    Ok(())
+}
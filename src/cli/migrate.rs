@@ -0,0 +1,88 @@
+use std::io::Error;
+
+use super::MigrateCommand;
+use crate::migration::{
+    applied_migrations, create_database_if_missing, schema_drift, unknown_migrations, MIGRATOR,
+};
+use crate::startup::get_connection_pool;
+use crate::DatabaseSettings;
+
+pub async fn run_migrate_command(
+    command: MigrateCommand,
+    configuration: &DatabaseSettings,
+) -> Result<(), Error> {
+    if let MigrateCommand::Up = command {
+        if create_database_if_missing(configuration)
+            .await
+            .map_err(Error::other)?
+        {
+            println!("Created database {}", configuration.database_name);
+        }
+    }
+    let db_pool = get_connection_pool(configuration);
+    let applied = applied_migrations(&db_pool).await.map_err(Error::other)?;
+    match command {
+        MigrateCommand::Up => {
+            MIGRATOR.run(&db_pool).await.map_err(Error::other)?;
+            for migration in MIGRATOR.iter().filter(|migration| {
+                migration.migration_type.is_up_migration() && !applied.contains(&migration.version)
+            }) {
+                println!("Applied {} {}", migration.version, migration.description);
+            }
+        }
+        MigrateCommand::Down => {
+            let Some((&latest, earlier)) = applied.split_last() else {
+                println!("No migrations to revert");
+                return Ok(());
+            };
+            let migration = MIGRATOR
+                .iter()
+                .find(|migration| {
+                    migration.version == latest && migration.migration_type.is_down_migration()
+                })
+                .ok_or_else(|| {
+                    Error::other(format!("Migration {} has no down migration", latest))
+                })?;
+            let target = earlier.last().copied().unwrap_or(0);
+            MIGRATOR
+                .undo(&db_pool, target)
+                .await
+                .map_err(Error::other)?;
+            println!("Reverted {} {}", migration.version, migration.description);
+        }
+        MigrateCommand::Status => {
+            for migration in MIGRATOR
+                .iter()
+                .filter(|migration| !migration.migration_type.is_down_migration())
+            {
+                let status = if applied.contains(&migration.version) {
+                    "applied"
+                } else {
+                    "pending"
+                };
+                println!("{} {} {}", migration.version, status, migration.description);
+            }
+            // Applied by a newer binary
+            for version in unknown_migrations(&applied) {
+                println!("{} unknown", version);
+            }
+        }
+        MigrateCommand::Check { query_data } => {
+            let drift = schema_drift(configuration, &query_data).await?;
+            db_pool.close().await;
+            if !drift.is_empty() {
+                for query in &drift {
+                    println!("{}", query);
+                }
+                return Err(Error::other(format!(
+                    "Schema drift in {} queries",
+                    drift.len()
+                )));
+            }
+            println!("No schema drift");
+            return Ok(());
+        }
+    }
+    db_pool.close().await;
+    Ok(())
+}
@@ -0,0 +1,124 @@
+use std::io::Error;
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand};
+
+use crate::configuration::{
+    configuration_directory, environment_name, load_configuration_with_sources, Settings,
+    ValueSources,
+};
+
+mod admin;
+mod config;
+mod migrate;
+mod seed;
+
+pub use admin::create_admin;
+pub use config::run_config_command;
+pub use migrate::run_migrate_command;
+pub use seed::{seed, FixtureUser};
+
+use crate::startup::get_connection_pool;
+
+#[derive(Debug, Parser)]
+#[command(name = "actix-template", version, about)]
+pub struct Cli {
+    /// Environment to load, defaults to APP_ENVIRONMENT or development
+    #[arg(long, global = true)]
+    pub environment: Option<String>,
+    // Starts the server when no command is given
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Run the HTTP and admin servers
+    Serve,
+    /// Inspect the configuration the server would run with
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+    /// Apply, revert, list or check the migrations embedded in the binary
+    Migrate {
+        #[command(subcommand)]
+        command: MigrateCommand,
+    },
+    /// Create a user allowed to use the admin endpoints
+    CreateAdmin {
+        #[arg(long)]
+        email: String,
+        #[arg(long, default_value = "Admin")]
+        name: String,
+        /// Read the password from stdin instead of prompting for it
+        #[arg(long)]
+        password_stdin: bool,
+    },
+    /// Load fixture users for development
+    Seed {
+        /// Seed an environment other than development or test, the fixture admin has a known password
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ConfigCommand {
+    /// Print every merged value, secrets masked, with where it came from
+    Show,
+    /// Validate the configuration, exiting non-zero when it is invalid
+    Check,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum MigrateCommand {
+    /// Create the database if needed and apply pending migrations
+    Up,
+    /// Revert the latest applied migration
+    Down,
+    /// List migrations and whether they are applied
+    Status,
+    /// Compare the live schema with the offline query data, exiting non-zero on drift
+    Check {
+        /// Directory written by `cargo sqlx prepare`
+        #[arg(long, default_value = ".sqlx")]
+        query_data: PathBuf,
+    },
+}
+
+// Settings for `--environment`, falling back to APP_ENVIRONMENT
+pub fn load_settings(environment: Option<String>) -> Result<(Settings, ValueSources), Error> {
+    let environment = environment.unwrap_or_else(environment_name);
+    load_configuration_with_sources(&configuration_directory(), &environment).map_err(Error::other)
+}
+
+// Run every command except serve, which the binary runs itself
+pub async fn run(command: Command, environment: Option<String>) -> Result<(), Error> {
+    match command {
+        Command::Serve => Err(Error::other("serve is run by the binary, not cli::run")),
+        Command::Config { command } => run_config_command(command, environment),
+        Command::Migrate { command } => {
+            let (settings, _) = load_settings(environment)?;
+            run_migrate_command(command, &settings.database).await
+        }
+        Command::CreateAdmin {
+            email,
+            name,
+            password_stdin,
+        } => {
+            let (settings, _) = load_settings(environment)?;
+            let db_pool = get_connection_pool(&settings.database);
+            let id = create_admin(&email, &name, password_stdin, &db_pool).await?;
+            println!("Created admin {} with id {}", email, id);
+            Ok(())
+        }
+        Command::Seed { force } => {
+            let (settings, _) = load_settings(environment)?;
+            let db_pool = get_connection_pool(&settings.database);
+            let inserted = seed(settings.environment.as_str(), force, &db_pool).await?;
+            println!("Inserted {} fixture users", inserted);
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,73 @@
+use std::io::Error;
+
+use secrecy::Secret;
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::authentication::hash_password;
+use crate::metrics::ConnectionPool;
+
+// Development users, every one with the password "password123"
+const FIXTURE_USERS: &str = include_str!("../../fixtures/users.json");
+
+#[derive(Debug, Deserialize)]
+pub struct FixtureUser {
+    pub name: String,
+    pub email: String,
+    pub password: String,
+    pub handle: Option<String>,
+    #[serde(default)]
+    pub is_admin: bool,
+}
+
+// Environments seeded without `--force`
+const SEEDABLE_ENVIRONMENTS: [&str; 2] = ["development", "test"];
+
+// Insert the fixture users that do not exist yet, returning how many were added
+pub async fn seed(
+    environment: &str,
+    force: bool,
+    db_pool: &ConnectionPool,
+) -> Result<usize, Error> {
+    if !force && !SEEDABLE_ENVIRONMENTS.contains(&environment) {
+        return Err(Error::other(format!(
+            "Refusing to seed fixture users in the {} environment, pass --force to seed anyway",
+            environment
+        )));
+    }
+    let users: Vec<FixtureUser> = serde_json::from_str(FIXTURE_USERS).map_err(Error::other)?;
+    let mut inserted = 0;
+    for user in &users {
+        if insert_fixture_user_repository(user, db_pool)
+            .await
+            .map_err(Error::other)?
+        {
+            inserted += 1;
+        }
+    }
+    Ok(inserted)
+}
+
+#[tracing::instrument(name = "Insert Fixture User In Database", skip(user, db_pool), fields(email = %user.email))]
+async fn insert_fixture_user_repository(
+    user: &FixtureUser,
+    db_pool: &ConnectionPool,
+) -> Result<bool, sqlx::Error> {
+    let password_hash = hash_password(Secret::new(user.password.clone())).await;
+    let result = sqlx::query!(
+        r#"
+        INSERT INTO users (id, name, email, password, handle, is_admin)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        ON CONFLICT DO NOTHING
+        "#,
+        Uuid::new_v4(),
+        user.name,
+        user.email,
+        password_hash,
+        user.handle,
+        user.is_admin
+    )
+    .execute(&mut *db_pool.acquire().await?)
+    .await?;
+    Ok(result.rows_affected() == 1)
+}
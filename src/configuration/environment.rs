@@ -1,27 +1,54 @@
-use std::str::FromStr;
+use std::path::Path;
 
-pub enum ConfigurationEnvironment {
-    Development,
-    Production,
-}
+use serde::Deserialize;
+
+// Files in the configuration directory that are not environments
+const NON_ENVIRONMENT_FILES: &[&str] = &["base", "local"];
+
+// Name of an environment, backed by `configuration/<name>.yaml`
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
+#[serde(transparent)]
+pub struct ConfigurationEnvironment(String);
 
 impl ConfigurationEnvironment {
-    pub fn as_str(&self) -> &str {
-        match self {
-            ConfigurationEnvironment::Development => "development",
-            ConfigurationEnvironment::Production => "production",
+    // Accept `name` when the configuration directory has an overlay for it
+    pub fn resolve(name: &str, configuration_directory: &Path) -> Result<Self, String> {
+        let name = name.to_lowercase();
+        let available = available_environments(configuration_directory);
+        if available.contains(&name) {
+            return Ok(Self(name));
         }
+        Err(format!(
+            "'{}' is not a valid configuration environment. Available environments: {}",
+            name,
+            if available.is_empty() {
+                "none".into()
+            } else {
+                available.join(", ")
+            }
+        ))
     }
-}
 
-impl FromStr for ConfigurationEnvironment {
-    type Err = String;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s.to_lowercase().as_str() {
-            "development" => Ok(ConfigurationEnvironment::Development),
-            "production" => Ok(ConfigurationEnvironment::Production),
-            _ => Err(format!("'{}' is not a valid configuration environment. Use either development or production", s)),
-        }
+    pub fn as_str(&self) -> &str {
+        &self.0
     }
 }
+
+// Every `<name>.yaml` overlay in the configuration directory, sorted by name
+pub fn available_environments(configuration_directory: &Path) -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(configuration_directory) else {
+        return Vec::new();
+    };
+    let mut environments: Vec<String> = entries
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            if path.extension()? != "yaml" {
+                return None;
+            }
+            let name = path.file_stem()?.to_str()?.to_owned();
+            (!NON_ENVIRONMENT_FILES.contains(&name.as_str())).then_some(name)
+        })
+        .collect();
+    environments.sort();
+    environments
+}
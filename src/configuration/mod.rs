@@ -1,23 +1,57 @@
 use config::Config;
-use std::str::FromStr;
+use std::path::{Path, PathBuf};
 
 mod environment;
 pub mod settings;
+mod show;
+mod sources;
+mod validation;
 
-use self::environment::ConfigurationEnvironment;
+pub use self::environment::{available_environments, ConfigurationEnvironment};
+pub use self::show::{describe, ConfigurationValue};
+pub use self::sources::{NamedValue, DATABASE_URL, SECRET_KEYS};
+pub use self::validation::*;
 pub use settings::*;
 
-pub fn get_configuration() -> Result<Settings, config::ConfigError> {
+pub fn get_configuration() -> Result<Settings, ConfigurationError> {
+    get_configuration_with_sources().map(|(settings, _)| settings)
+}
+
+// Settings along with where each value came from
+pub fn get_configuration_with_sources() -> Result<(Settings, ValueSources), ConfigurationError> {
+    load_configuration_with_sources(&configuration_directory(), &environment_name())
+}
+
+// The configuration directory in the working directory
+pub fn configuration_directory() -> PathBuf {
     // Get current directory path
     let base_path = std::env::current_dir().expect("Failed to get current directory.");
-    // Get the configuration directory
-    let configuration_directory = base_path.join("configuration");
-    // Get the current environment
-    let environment = std::env::var("APP_ENVIRONMENT").unwrap_or_else(|_| "development".into());
-    let environment = ConfigurationEnvironment::from_str(environment.as_str())
-        .expect("Invalid APP_ENVIRONMENT variable value.");
-
-    let config = Config::builder()
+    base_path.join("configuration")
+}
+
+// Environment named by APP_ENVIRONMENT, development by default
+pub fn environment_name() -> String {
+    std::env::var("APP_ENVIRONMENT").unwrap_or_else(|_| "development".into())
+}
+
+pub fn load_configuration(
+    configuration_directory: &Path,
+    environment: &str,
+) -> Result<Settings, ConfigurationError> {
+    load_configuration_with_sources(configuration_directory, environment)
+        .map(|(settings, _)| settings)
+}
+
+// Layer base.yaml, <environment>.yaml, the optional local.yaml, DATABASE_URL and
+// APP__ variables, read `*_file` secrets, then validate the result
+pub fn load_configuration_with_sources(
+    configuration_directory: &Path,
+    environment: &str,
+) -> Result<(Settings, ValueSources), ConfigurationError> {
+    let environment = ConfigurationEnvironment::resolve(environment, configuration_directory)
+        .map_err(config::ConfigError::Message)?;
+
+    let mut config = Config::builder()
         // Add default base configuration
         .add_source(config::File::from(configuration_directory.join("base.yaml")).required(true))
         // add environment configuration
@@ -27,13 +61,37 @@ pub fn get_configuration() -> Result<Settings, config::ConfigError> {
             )
             .required(true),
         )
+        // Add developer overrides, not checked in
+        .add_source(config::File::from(configuration_directory.join("local.yaml")).required(false))
+        // Platform provided connection string
+        .add_source(NamedValue::from_env("database.url", DATABASE_URL))
         // Add Runtime environment configuration
         .add_source(
             config::Environment::with_prefix("APP")
                 .prefix_separator("__")
                 .separator("_"),
         )
+        // Remember which environment the settings were loaded for
+        .add_source(NamedValue::new(
+            "environment",
+            "APP_ENVIRONMENT",
+            Some(environment.as_str().into()),
+        ))
         .build()?;
-    let settings = config.try_deserialize::<Settings>()?;
-    Ok(settings)
+    let mut problems = sources::read_secret_files(&mut config.cache);
+    let sources = ValueSources::new(config.cache.clone());
+    let mut settings = match config.try_deserialize::<Settings>() {
+        Ok(settings) => settings,
+        // A missing secret file usually explains the missing field
+        Err(_) if !problems.is_empty() => return Err(ConfigurationError::Invalid(problems)),
+        Err(e) => return Err(deserialize_error(e, &sources)),
+    };
+    if let Err(message) = settings.database.apply_url() {
+        problems.push(ConfigurationProblem::new("database.url", message, &sources));
+    }
+    problems.extend(validate(&settings, &sources));
+    if !problems.is_empty() {
+        return Err(ConfigurationError::Invalid(problems));
+    }
+    Ok((settings, sources))
 }
@@ -1,24 +1,45 @@
+use std::time::Duration;
+
 use secrecy::{ExposeSecret, Secret};
 use serde::Deserialize;
-use serde_aux::prelude::deserialize_number_from_string;
-use sqlx::postgres::{PgConnectOptions, PgSslMode};
+use serde_aux::prelude::{deserialize_number_from_string, deserialize_option_number_from_string};
+use sqlx::postgres::{PgConnectOptions, PgPoolOptions, PgSslMode};
 use sqlx::ConnectOptions;
 use tracing_log::log;
 
-#[derive(Debug, Deserialize)]
+use super::ConfigurationEnvironment;
+use crate::email_client::EmailClient;
+
+#[derive(Debug, Deserialize, Clone)]
 pub struct Settings {
+    // Set from APP_ENVIRONMENT when loading, not read from the files
+    pub environment: ConfigurationEnvironment,
     pub application: ApplicationSettings,
     pub database: DatabaseSettings,
+    pub storage: StorageSettings,
+    pub email_client: EmailClientSettings,
+    pub telemetry: TelemetrySettings,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Clone)]
 pub struct ApplicationSettings {
     pub host_address: String,
     // Standard serde will fail to pick up integer from config
     pub port: u16,
+    // Serves operational endpoints like /metrics, kept off the public port
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub admin_port: u16,
+    // Public URL used to build links sent to users
+    pub base_url: String,
+    // Seconds in-flight requests get to finish once shutdown starts
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub shutdown_timeout_seconds: u64,
+    // How long /ready reports 503 before draining starts, so load balancers stop routing
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub shutdown_readiness_delay_milliseconds: u64,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Clone)]
 pub struct DatabaseSettings {
     pub database_name: String,
     pub host: String,
@@ -28,29 +49,383 @@ pub struct DatabaseSettings {
     #[serde(deserialize_with = "deserialize_number_from_string")]
     pub port: u16,
     pub username: String,
-    // For ssl mode
-    pub ssl_mode: bool,
+    // disable, allow, prefer, require, verify-ca or verify-full; true and false
+    // are still accepted as require and prefer
+    #[serde(deserialize_with = "deserialize_ssl_mode")]
+    pub ssl_mode: PgSslMode,
+    // CA used to verify the server with verify-ca and verify-full
+    #[serde(default)]
+    pub ssl_root_cert: Option<String>,
+    // Client certificate and key for mutual TLS
+    #[serde(default)]
+    pub ssl_client_cert: Option<String>,
+    #[serde(default)]
+    pub ssl_client_key: Option<String>,
+    // Level SQL statements are logged at
+    pub log_statements: StatementLogLevel,
+    // Statements slower than this are logged at `slow_statement_level`
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub slow_statement_threshold_milliseconds: u64,
+    pub slow_statement_level: StatementLogLevel,
+    // postgres:// URL, e.g. from DATABASE_URL, overriding the fields above
+    #[serde(default)]
+    pub url: Option<Secret<String>>,
+    // Shown in pg_stat_activity
+    pub application_name: String,
+    // Server side limit for a single statement, 0 disables it
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub statement_timeout_milliseconds: u64,
+    // Prepared statements cached per connection
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub statement_cache_capacity: usize,
+    pub pool: PoolSettings,
+    // Apply embedded migrations before serving, off by default
+    #[serde(default)]
+    pub run_migrations_on_startup: bool,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct PoolSettings {
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub min_connections: u32,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub max_connections: u32,
+    // How long a request waits for a free connection
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub acquire_timeout_milliseconds: u64,
+    // Close connections idle or open for longer than this, unset keeps them
+    #[serde(default, deserialize_with = "deserialize_option_number_from_string")]
+    pub idle_timeout_seconds: Option<u64>,
+    #[serde(default, deserialize_with = "deserialize_option_number_from_string")]
+    pub max_lifetime_seconds: Option<u64>,
+    // Ping connections before handing them out
+    pub test_before_acquire: bool,
+    // Connect and run a query at startup instead of on the first request
+    pub connect_eagerly: bool,
+}
+
+impl PoolSettings {
+    pub fn options(&self) -> PgPoolOptions {
+        PgPoolOptions::new()
+            .min_connections(self.min_connections)
+            .max_connections(self.max_connections)
+            .acquire_timeout(Duration::from_millis(self.acquire_timeout_milliseconds))
+            .idle_timeout(self.idle_timeout_seconds.map(Duration::from_secs))
+            .max_lifetime(self.max_lifetime_seconds.map(Duration::from_secs))
+            .test_before_acquire(self.test_before_acquire)
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum StatementLogLevel {
+    Off,
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl From<StatementLogLevel> for log::LevelFilter {
+    fn from(level: StatementLogLevel) -> Self {
+        match level {
+            StatementLogLevel::Off => log::LevelFilter::Off,
+            StatementLogLevel::Error => log::LevelFilter::Error,
+            StatementLogLevel::Warn => log::LevelFilter::Warn,
+            StatementLogLevel::Info => log::LevelFilter::Info,
+            StatementLogLevel::Debug => log::LevelFilter::Debug,
+            StatementLogLevel::Trace => log::LevelFilter::Trace,
+        }
+    }
 }
 
 impl DatabaseSettings {
+    // Copy host, port, credentials, database name and ssl settings from `url`,
+    // returning the keys it set
+    pub fn apply_url(&mut self) -> Result<Vec<&'static str>, String> {
+        let Some(url) = &self.url else {
+            return Ok(Vec::new());
+        };
+        let url = reqwest::Url::parse(url.expose_secret())
+            .map_err(|e| format!("must be a postgres:// URL: {}", e))?;
+        if !matches!(url.scheme(), "postgres" | "postgresql") {
+            return Err("must be a postgres:// URL".into());
+        }
+        let mut keys = Vec::new();
+        if let Some(host) = url.host_str() {
+            self.host = host.into();
+            keys.push("database.host");
+        }
+        if let Some(port) = url.port() {
+            self.port = port;
+            keys.push("database.port");
+        }
+        if !url.username().is_empty() {
+            self.username = percent_decode(url.username())?;
+            keys.push("database.username");
+        }
+        if let Some(password) = url.password() {
+            self.password = Secret::new(percent_decode(password)?);
+            keys.push("database.password");
+        }
+        let database_name = url.path().trim_start_matches('/');
+        if !database_name.is_empty() {
+            self.database_name = percent_decode(database_name)?;
+            keys.push("database.database_name");
+        }
+        for (name, value) in url.query_pairs() {
+            match name.as_ref() {
+                "sslmode" => {
+                    self.ssl_mode = value
+                        .parse()
+                        .map_err(|_| format!("has an unknown sslmode '{}'", value))?;
+                    keys.push("database.ssl_mode");
+                }
+                "sslrootcert" => {
+                    self.ssl_root_cert = Some(value.into());
+                    keys.push("database.ssl_root_cert");
+                }
+                "sslcert" => {
+                    self.ssl_client_cert = Some(value.into());
+                    keys.push("database.ssl_client_cert");
+                }
+                "sslkey" => {
+                    self.ssl_client_key = Some(value.into());
+                    keys.push("database.ssl_client_key");
+                }
+                _ => {}
+            }
+        }
+        Ok(keys)
+    }
+
     // Get without database
     pub fn without_database(&self) -> PgConnectOptions {
-        PgConnectOptions::new()
+        let mut options = PgConnectOptions::new()
             .host(&self.host)
             .port(self.port)
             .username(&self.username)
             .password(self.password.expose_secret())
-            .ssl_mode(if self.ssl_mode {
-                PgSslMode::Require
-            } else {
-                PgSslMode::Prefer
-            })
+            .ssl_mode(self.ssl_mode);
+        if let Some(root_cert) = &self.ssl_root_cert {
+            options = options.ssl_root_cert(root_cert);
+        }
+        if let Some(client_cert) = &self.ssl_client_cert {
+            options = options.ssl_client_cert(client_cert);
+        }
+        if let Some(client_key) = &self.ssl_client_key {
+            options = options.ssl_client_key(client_key);
+        }
+        options
     }
 
     // Get with database
     pub fn with_database(&self) -> PgConnectOptions {
-        let mut options = self.without_database().database(&self.database_name);
-        options.log_statements(log::LevelFilter::Trace);
-        options
+        let options = self
+            .without_database()
+            .database(&self.database_name)
+            .application_name(&self.application_name)
+            .statement_cache_capacity(self.statement_cache_capacity)
+            .log_statements(self.log_statements.into())
+            .log_slow_statements(
+                self.slow_statement_level.into(),
+                Duration::from_millis(self.slow_statement_threshold_milliseconds),
+            );
+        if self.statement_timeout_milliseconds == 0 {
+            return options;
+        }
+        options.options([(
+            "statement_timeout",
+            self.statement_timeout_milliseconds.to_string(),
+        )])
     }
 }
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct StorageSettings {
+    pub backend: StorageBackend,
+    // Root directory for the local backend
+    pub local_path: String,
+    // Required when backend is s3
+    pub s3: Option<S3Settings>,
+    // Maximum accepted avatar upload in bytes
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub max_avatar_size: usize,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackend {
+    Local,
+    S3,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct S3Settings {
+    pub bucket: String,
+    pub region: String,
+    // e.g. https://s3.eu-west-2.amazonaws.com or http://127.0.0.1:9000 for MinIO
+    pub endpoint: String,
+    pub access_key: String,
+    pub secret_key: Secret<String>,
+    // Address the bucket as part of the path instead of the host name
+    pub path_style: bool,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct EmailClientSettings {
+    pub base_url: String,
+    pub sender_email: String,
+    pub authorization_token: Secret<String>,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub timeout_milliseconds: u64,
+}
+
+impl EmailClientSettings {
+    pub fn client(&self) -> EmailClient {
+        EmailClient::new(
+            self.base_url.clone(),
+            self.sender_email.clone(),
+            self.authorization_token.clone(),
+            Duration::from_millis(self.timeout_milliseconds),
+        )
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct TelemetrySettings {
+    pub format: LogFormat,
+    // EnvFilter directives, e.g. "info,sqlx=warn", RUST_LOG takes precedence
+    pub filter: String,
+    pub destination: LogDestination,
+    // Required when logging to a file
+    pub file: Option<LogFileSettings>,
+    // How sensitive fields are rendered in logs, standard rules when omitted
+    #[serde(default = "default_redaction_rules")]
+    pub redaction: Vec<RedactionRule>,
+    // Spans are only exported when set
+    pub opentelemetry: Option<OpenTelemetrySettings>,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    // Structured JSON for log aggregation
+    Bunyan,
+    // Single line per event
+    Compact,
+    // Multi-line with colours, for local development
+    Pretty,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogDestination {
+    Stdout,
+    Stderr,
+    File,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct LogFileSettings {
+    pub directory: String,
+    pub file_name: String,
+    pub rotation: LogRotation,
+    // Size a file may reach before rotating, used with size rotation
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub max_size_bytes: usize,
+    // Rotated files kept besides the current one
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub max_files: usize,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogRotation {
+    Size,
+    Daily,
+}
+
+// Applies to fields named `field` or ending in `_<field>`, e.g. `email` also covers `new_email`
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
+pub struct RedactionRule {
+    pub field: String,
+    pub action: RedactionAction,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RedactionAction {
+    // Replace with a stable hash so entries can still be correlated
+    Hash,
+    // Leave the field out entirely
+    Drop,
+    // Keep only the last characters
+    Mask,
+}
+
+pub fn default_redaction_rules() -> Vec<RedactionRule> {
+    [
+        ("email", RedactionAction::Hash),
+        ("recipient", RedactionAction::Hash),
+        ("password", RedactionAction::Drop),
+        ("password_confirmation", RedactionAction::Drop),
+        ("token", RedactionAction::Mask),
+    ]
+    .into_iter()
+    .map(|(field, action)| RedactionRule {
+        field: field.into(),
+        action,
+    })
+    .collect()
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct OpenTelemetrySettings {
+    // Collector URL, e.g. http://127.0.0.1:4317 for gRPC or http://127.0.0.1:4318 for HTTP
+    pub endpoint: String,
+    pub protocol: OtlpProtocol,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum OtlpProtocol {
+    Grpc,
+    Http,
+}
+
+// Accepts a mode name or the older boolean form
+fn deserialize_ssl_mode<'de, D>(deserializer: D) -> Result<PgSslMode, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum SslMode {
+        Bool(bool),
+        Name(String),
+    }
+
+    match SslMode::deserialize(deserializer)? {
+        SslMode::Bool(true) => Ok(PgSslMode::Require),
+        SslMode::Bool(false) => Ok(PgSslMode::Prefer),
+        SslMode::Name(name) => match name.as_str() {
+            "true" => Ok(PgSslMode::Require),
+            "false" => Ok(PgSslMode::Prefer),
+            name => name.parse().map_err(|_| {
+                serde::de::Error::custom(format!(
+                    "unknown ssl mode '{}', expected one of disable, allow, prefer, require, verify-ca, verify-full",
+                    name
+                ))
+            }),
+        },
+    }
+}
+
+fn percent_decode(value: &str) -> Result<String, String> {
+    percent_encoding::percent_decode_str(value)
+        .decode_utf8()
+        .map(Into::into)
+        .map_err(|e| format!("is not valid UTF-8 once decoded: {}", e))
+}
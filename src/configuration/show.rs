@@ -0,0 +1,86 @@
+use std::fmt;
+
+use sqlx::postgres::PgSslMode;
+
+use super::{DatabaseSettings, Settings, ValueSources, SECRET_KEYS};
+
+// Shown instead of secret values
+const MASK: &str = "********";
+
+// One effective setting and where its value came from
+#[derive(Debug, Clone)]
+pub struct ConfigurationValue {
+    pub key: String,
+    pub value: String,
+    pub origin: String,
+}
+
+impl fmt::Display for ConfigurationValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} = {}  # {}", self.key, self.value, self.origin)
+    }
+}
+
+// Every merged value with secrets masked, sorted by key
+pub fn describe(settings: &Settings, sources: &ValueSources) -> Vec<ConfigurationValue> {
+    let mut values: Vec<_> = sources
+        .values()
+        .into_iter()
+        .map(|(key, value)| ConfigurationValue {
+            value: if SECRET_KEYS.contains(&key.as_str()) {
+                MASK.into()
+            } else {
+                value.to_string()
+            },
+            origin: sources.source_of(&key).unwrap_or_else(|| "unknown".into()),
+            key,
+        })
+        .collect();
+
+    // Fields taken from database.url replace the ones from files
+    let mut database = settings.database.clone();
+    let url_keys = database.apply_url().unwrap_or_default();
+    let url_origin = sources
+        .source_of("database.url")
+        .unwrap_or_else(|| "unknown".into());
+    for key in url_keys {
+        let value = ConfigurationValue {
+            key: key.into(),
+            value: database_value(&database, key),
+            origin: format!("database.url from {}", url_origin),
+        };
+        match values.iter_mut().find(|existing| existing.key == key) {
+            Some(existing) => *existing = value,
+            None => values.push(value),
+        }
+    }
+    values.sort_by(|a, b| a.key.cmp(&b.key));
+    values
+}
+
+fn database_value(database: &DatabaseSettings, key: &str) -> String {
+    let optional = |value: &Option<String>| value.clone().unwrap_or_default();
+    match key {
+        "database.host" => database.host.clone(),
+        "database.port" => database.port.to_string(),
+        "database.username" => database.username.clone(),
+        "database.password" => MASK.into(),
+        "database.database_name" => database.database_name.clone(),
+        "database.ssl_mode" => ssl_mode_name(database.ssl_mode).into(),
+        "database.ssl_root_cert" => optional(&database.ssl_root_cert),
+        "database.ssl_client_cert" => optional(&database.ssl_client_cert),
+        "database.ssl_client_key" => optional(&database.ssl_client_key),
+        _ => String::new(),
+    }
+}
+
+fn ssl_mode_name(mode: PgSslMode) -> &'static str {
+    match mode {
+        PgSslMode::Disable => "disable",
+        PgSslMode::Allow => "allow",
+        PgSslMode::Prefer => "prefer",
+        PgSslMode::Require => "require",
+        PgSslMode::VerifyCa => "verify-ca",
+        PgSslMode::VerifyFull => "verify-full",
+    }
+}
@@ -0,0 +1,109 @@
+use config::{Map, Source, Value, ValueKind};
+
+use super::validation::{ConfigurationProblem, ValueSources};
+
+// Connection string injected by the platform
+pub const DATABASE_URL: &str = "DATABASE_URL";
+
+// Settings that can also be read from a file named by `<key>_file`
+pub const SECRET_KEYS: [&str; 4] = [
+    "database.password",
+    "database.url",
+    "storage.s3.secret_key",
+    "email_client.authorization_token",
+];
+
+// A single value with a named origin, e.g. DATABASE_URL for `database.url`
+#[derive(Debug, Clone)]
+pub struct NamedValue {
+    key: &'static str,
+    origin: &'static str,
+    value: Option<String>,
+}
+
+impl NamedValue {
+    pub fn new(key: &'static str, origin: &'static str, value: Option<String>) -> Self {
+        Self { key, origin, value }
+    }
+
+    // Read from the environment variable `origin`
+    pub fn from_env(key: &'static str, origin: &'static str) -> Self {
+        Self::new(key, origin, std::env::var(origin).ok())
+    }
+}
+
+impl Source for NamedValue {
+    fn clone_into_box(&self) -> Box<dyn Source + Send + Sync> {
+        Box::new(self.clone())
+    }
+
+    fn collect(&self) -> Result<Map<String, Value>, config::ConfigError> {
+        let mut map = Map::new();
+        if let Some(value) = &self.value {
+            let origin = self.origin.to_string();
+            map.insert(
+                self.key.to_string(),
+                Value::new(Some(&origin), value.as_str()),
+            );
+        }
+        Ok(map)
+    }
+}
+
+// Replace every secret that has a `<key>_file` with the contents of that file
+pub fn read_secret_files(root: &mut Value) -> Vec<ConfigurationProblem> {
+    let mut problems = Vec::new();
+    for key in SECRET_KEYS {
+        // APP__X_FILE variables are split on `_`, so also look for the nested form
+        let candidates = [
+            format!("{}_file", key),
+            format!("{}.file", key.replace('_', ".")),
+        ];
+        let Some((file_key, path)) = candidates.into_iter().find_map(|candidate| {
+            let path = lookup(root, &candidate)?.clone().into_string().ok()?;
+            Some((candidate, path))
+        }) else {
+            continue;
+        };
+
+        match std::fs::read_to_string(&path) {
+            Ok(contents) => {
+                let contents = contents.trim_end_matches(['\r', '\n']);
+                insert(root, key, Value::new(Some(&path), contents));
+            }
+            Err(e) => {
+                let sources = ValueSources::new(root.clone());
+                problems.push(ConfigurationProblem::new(
+                    &file_key,
+                    format!("could not read {}: {}", path, e),
+                    &sources,
+                ));
+            }
+        }
+    }
+    problems
+}
+
+fn lookup<'a>(root: &'a Value, key: &str) -> Option<&'a Value> {
+    key.split('.')
+        .try_fold(root, |value, segment| match &value.kind {
+            ValueKind::Table(table) => table.get(segment),
+            _ => None,
+        })
+}
+
+fn insert(root: &mut Value, key: &str, new_value: Value) {
+    let (parent, field) = key.rsplit_once('.').unwrap_or(("", key));
+    let mut value = root;
+    for segment in parent.split('.').filter(|segment| !segment.is_empty()) {
+        value = match &mut value.kind {
+            ValueKind::Table(table) => table
+                .entry(segment.to_string())
+                .or_insert_with(|| Value::new(None, Map::<String, Value>::new())),
+            _ => return,
+        };
+    }
+    if let ValueKind::Table(table) = &mut value.kind {
+        table.insert(field.to_string(), new_value);
+    }
+}
@@ -0,0 +1,308 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+
+use config::{Value, ValueKind};
+use tracing_subscriber::EnvFilter;
+
+use super::settings::*;
+
+// Origin config gives values read from environment variables
+const ENVIRONMENT_ORIGIN: &str = "the environment";
+
+// A single invalid setting and where its value came from
+#[derive(Debug, Clone)]
+pub struct ConfigurationProblem {
+    pub key: String,
+    pub message: String,
+    pub source: Option<String>,
+}
+
+impl ConfigurationProblem {
+    pub fn new(key: &str, message: impl Into<String>, sources: &ValueSources) -> Self {
+        Self {
+            key: key.into(),
+            message: message.into(),
+            source: sources.source_of(key),
+        }
+    }
+}
+
+impl fmt::Display for ConfigurationProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.key, self.message)?;
+        match &self.source {
+            Some(source) => write!(f, " (set in {})", source),
+            None => write!(f, " (not set)"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigurationError {
+    // Files could not be read or merged
+    Load(config::ConfigError),
+    // Every problem found, not just the first one
+    Invalid(Vec<ConfigurationProblem>),
+}
+
+impl fmt::Display for ConfigurationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigurationError::Load(e) => write!(f, "{}", e),
+            ConfigurationError::Invalid(problems) => {
+                write!(f, "Invalid configuration:")?;
+                for problem in problems {
+                    write!(f, "\n  - {}", problem)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigurationError {}
+
+impl From<config::ConfigError> for ConfigurationError {
+    fn from(e: config::ConfigError) -> Self {
+        ConfigurationError::Load(e)
+    }
+}
+
+// Merged configuration tree, used to tell where each value came from
+#[derive(Clone)]
+pub struct ValueSources(Value);
+
+// Holds raw secrets, so only say that it exists
+impl fmt::Debug for ValueSources {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ValueSources").finish_non_exhaustive()
+    }
+}
+
+impl ValueSources {
+    pub fn new(root: Value) -> Self {
+        Self(root)
+    }
+
+    // File path or environment variable that set `key`, e.g. `database.port`
+    pub fn source_of(&self, key: &str) -> Option<String> {
+        let mut value = &self.0;
+        for segment in key.split('.') {
+            match &value.kind {
+                ValueKind::Table(table) => value = table.get(segment)?,
+                ValueKind::Array(array) => value = array.get(segment.parse::<usize>().ok()?)?,
+                _ => return None,
+            }
+        }
+        match value.origin()? {
+            ENVIRONMENT_ORIGIN => Some(environment_variable(key)),
+            origin => Some(origin.to_owned()),
+        }
+    }
+}
+
+impl ValueSources {
+    // Every leaf value keyed by its dotted path, array items by their index
+    pub fn values(&self) -> BTreeMap<String, &Value> {
+        let mut values = BTreeMap::new();
+        collect_values(String::new(), &self.0, &mut values);
+        values
+    }
+}
+
+fn collect_values<'a>(key: String, value: &'a Value, values: &mut BTreeMap<String, &'a Value>) {
+    let join = |segment: &dyn fmt::Display| match key.as_str() {
+        "" => segment.to_string(),
+        key => format!("{}.{}", key, segment),
+    };
+    match &value.kind {
+        ValueKind::Table(table) => {
+            for (segment, value) in table {
+                collect_values(join(segment), value, values);
+            }
+        }
+        ValueKind::Array(array) => {
+            for (index, value) in array.iter().enumerate() {
+                collect_values(join(&index), value, values);
+            }
+        }
+        _ => {
+            values.insert(key, value);
+        }
+    }
+}
+
+// Variable name for `key` following the APP__ prefix and `_` separator
+pub fn environment_variable(key: &str) -> String {
+    format!("APP__{}", key.replace('.', "_").to_uppercase())
+}
+
+// Turn a deserialization error about a known key into a problem with its source
+pub fn deserialize_error(e: config::ConfigError, sources: &ValueSources) -> ConfigurationError {
+    match &e {
+        config::ConfigError::Type { key: Some(key), .. } => {
+            ConfigurationError::Invalid(vec![ConfigurationProblem::new(
+                key,
+                e.to_string(),
+                sources,
+            )])
+        }
+        _ => ConfigurationError::Load(e),
+    }
+}
+
+struct Problems<'a> {
+    sources: &'a ValueSources,
+    problems: Vec<ConfigurationProblem>,
+}
+
+impl Problems<'_> {
+    fn check(&mut self, is_valid: bool, key: &str, message: &str) {
+        if !is_valid {
+            self.problems
+                .push(ConfigurationProblem::new(key, message, self.sources));
+        }
+    }
+
+    fn check_not_empty(&mut self, value: &str, key: &str) {
+        self.check(!value.trim().is_empty(), key, "must not be empty");
+    }
+
+    fn check_file(&mut self, path: &str, key: &str) {
+        let is_valid = Path::new(path).is_file();
+        self.check(is_valid, key, &format!("file {} does not exist", path));
+    }
+
+    fn check_url(&mut self, value: &str, key: &str) {
+        let is_valid = reqwest::Url::parse(value)
+            .is_ok_and(|url| matches!(url.scheme(), "http" | "https") && url.has_host());
+        self.check(is_valid, key, "must be an http(s) URL");
+    }
+}
+
+// Check the settings as a whole, collecting every problem at once
+pub fn validate(settings: &Settings, sources: &ValueSources) -> Vec<ConfigurationProblem> {
+    let mut problems = Problems {
+        sources,
+        problems: Vec::new(),
+    };
+
+    // Application, port 0 picks a random free port
+    let application = &settings.application;
+    problems.check_not_empty(&application.host_address, "application.host_address");
+    problems.check(
+        application.port == 0 || application.port != application.admin_port,
+        "application.admin_port",
+        "must differ from application.port",
+    );
+    problems.check_url(&application.base_url, "application.base_url");
+
+    // Database
+    let database = &settings.database;
+    problems.check_not_empty(&database.host, "database.host");
+    problems.check(
+        database.port != 0,
+        "database.port",
+        "must be between 1 and 65535",
+    );
+    problems.check_not_empty(&database.username, "database.username");
+    problems.check_not_empty(&database.database_name, "database.database_name");
+    for (path, key) in [
+        (&database.ssl_root_cert, "database.ssl_root_cert"),
+        (&database.ssl_client_cert, "database.ssl_client_cert"),
+        (&database.ssl_client_key, "database.ssl_client_key"),
+    ] {
+        if let Some(path) = path {
+            problems.check_file(path, key);
+        }
+    }
+    problems.check(
+        database.ssl_client_cert.is_some() == database.ssl_client_key.is_some(),
+        "database.ssl_client_key",
+        "must be set together with database.ssl_client_cert",
+    );
+    let pool = &database.pool;
+    problems.check(
+        pool.max_connections > 0,
+        "database.pool.max_connections",
+        "must be greater than 0",
+    );
+    problems.check(
+        pool.min_connections <= pool.max_connections,
+        "database.pool.min_connections",
+        "must not exceed database.pool.max_connections",
+    );
+    problems.check(
+        pool.acquire_timeout_milliseconds > 0,
+        "database.pool.acquire_timeout_milliseconds",
+        "must be greater than 0",
+    );
+
+    // Storage
+    let storage = &settings.storage;
+    match storage.backend {
+        StorageBackend::Local => {
+            problems.check_not_empty(&storage.local_path, "storage.local_path")
+        }
+        StorageBackend::S3 => match &storage.s3 {
+            Some(s3) => {
+                problems.check_not_empty(&s3.bucket, "storage.s3.bucket");
+                problems.check_not_empty(&s3.region, "storage.s3.region");
+            }
+            None => problems.check(
+                false,
+                "storage.s3",
+                "must be set when storage.backend is s3",
+            ),
+        },
+    }
+    problems.check(
+        storage.max_avatar_size > 0,
+        "storage.max_avatar_size",
+        "must be greater than 0",
+    );
+
+    // Email client
+    let email_client = &settings.email_client;
+    problems.check_url(&email_client.base_url, "email_client.base_url");
+    problems.check(
+        validator::validate_email(&email_client.sender_email),
+        "email_client.sender_email",
+        "must be an email address",
+    );
+    problems.check(
+        email_client.timeout_milliseconds > 0,
+        "email_client.timeout_milliseconds",
+        "must be greater than 0",
+    );
+
+    // Telemetry
+    let telemetry = &settings.telemetry;
+    problems.check(
+        EnvFilter::try_new(&telemetry.filter).is_ok(),
+        "telemetry.filter",
+        "must be valid filter directives",
+    );
+    match &telemetry.file {
+        Some(file) => {
+            problems.check_not_empty(&file.directory, "telemetry.file.directory");
+            problems.check_not_empty(&file.file_name, "telemetry.file.file_name");
+            problems.check(
+                file.rotation != LogRotation::Size || file.max_size_bytes > 0,
+                "telemetry.file.max_size_bytes",
+                "must be greater than 0 with size rotation",
+            );
+        }
+        None => problems.check(
+            telemetry.destination != LogDestination::File,
+            "telemetry.file",
+            "must be set when telemetry.destination is file",
+        ),
+    }
+    if let Some(opentelemetry) = &telemetry.opentelemetry {
+        problems.check_url(&opentelemetry.endpoint, "telemetry.opentelemetry.endpoint");
+    }
+
+    problems.problems
+}
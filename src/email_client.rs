@@ -0,0 +1,77 @@
+use std::time::Duration;
+
+use reqwest::Client;
+use secrecy::{ExposeSecret, Secret};
+use serde::Serialize;
+
+use crate::request_id::{RequestId, REQUEST_ID_HEADER};
+
+// Client for the transactional email HTTP API (Postmark compatible)
+pub struct EmailClient {
+    http_client: Client,
+    base_url: String,
+    sender: String,
+    authorization_token: Secret<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct SendEmailRequest<'a> {
+    from: &'a str,
+    to: &'a str,
+    subject: &'a str,
+    html_body: &'a str,
+    text_body: &'a str,
+}
+
+impl EmailClient {
+    pub fn new(
+        base_url: String,
+        sender: String,
+        authorization_token: Secret<String>,
+        timeout: Duration,
+    ) -> Self {
+        let http_client = Client::builder()
+            .timeout(timeout)
+            .build()
+            .expect("Failed to build email HTTP client.");
+        Self {
+            http_client,
+            base_url,
+            sender,
+            authorization_token,
+        }
+    }
+
+    // The request id is forwarded so the email API's logs can be correlated with ours
+    #[tracing::instrument(name = "Send Email", skip(self, html_content, text_content))]
+    pub async fn send_email(
+        &self,
+        request_id: &RequestId,
+        recipient: &str,
+        subject: &str,
+        html_content: &str,
+        text_content: &str,
+    ) -> Result<(), reqwest::Error> {
+        let url = format!("{}/email", self.base_url);
+        let request_body = SendEmailRequest {
+            from: &self.sender,
+            to: recipient,
+            subject,
+            html_body: html_content,
+            text_body: text_content,
+        };
+        self.http_client
+            .post(&url)
+            .header(
+                "X-Postmark-Server-Token",
+                self.authorization_token.expose_secret(),
+            )
+            .header(REQUEST_ID_HEADER.as_str(), request_id.as_str())
+            .json(&request_body)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
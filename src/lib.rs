@@ -1,6 +1,17 @@
+pub mod audit;
+pub mod authentication;
+pub mod cli;
 pub mod configuration;
+pub mod email_client;
+pub mod metrics;
+pub mod migration;
+pub mod reload;
+pub mod request_id;
 pub mod routes;
+pub mod shutdown;
 pub mod startup;
+pub mod storage;
 pub mod telemetry;
+pub mod validation;
 pub use configuration::*;
 pub use startup::*;
@@ -1,28 +1,72 @@
-use std::{io::Error, net::TcpListener, time::Duration};
+use std::io::{Error, Write};
 
-use actix_template::{configuration, startup, telemetry};
-use sqlx::postgres::PgPoolOptions;
+use actix_template::cli::{self, Cli, Command};
+use actix_template::{configuration, reload, telemetry, Application};
+use clap::Parser;
 
 #[actix_web::main]
 async fn main() -> Result<(), Error> {
-    // Setup logger
-    let subscriber =
-        telemetry::get_subscriber("actix-template".into(), "debug".into(), std::io::stdout);
-    telemetry::init_subscriber(subscriber);
-
-    let configuration = configuration::get_configuration().expect("Failed to read configuration.");
+    let cli = Cli::parse();
+    match cli.command.unwrap_or(Command::Serve) {
+        Command::Serve => serve(cli.environment).await,
+        command => {
+            if let Err(e) = cli::run(command, cli.environment).await {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+            Ok(())
+        }
+    }
+}
 
-    // Setup PostgreSQL connection pool
-    let connection_pool = PgPoolOptions::new()
-        .acquire_timeout(Duration::from_secs(2))
-        .connect_lazy_with(configuration.database.with_database());
+// Run the HTTP and admin servers until shutdown
+async fn serve(environment: Option<String>) -> Result<(), Error> {
+    let (configuration, sources) = match cli::load_settings(environment) {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            eprintln!("Failed to read configuration: {}", e);
+            std::process::exit(1);
+        }
+    };
 
-    // Create Tcp listener
-    let address = format!(
-        "{}:{}",
-        configuration.application.host_address, configuration.application.port
+    // Setup logger as configured, exporting spans when OpenTelemetry is set
+    let redaction = telemetry::Redaction::new(configuration.telemetry.redaction.clone());
+    let tracer = configuration
+        .telemetry
+        .opentelemetry
+        .as_ref()
+        .map(|settings| telemetry::get_tracer("actix-template".into(), settings, redaction.clone()))
+        .transpose()
+        .map_err(Error::other)?;
+    // Held until main returns so buffered file logs are written out
+    let (writer, _writer_guard) = telemetry::get_writer(&configuration.telemetry)?;
+    let (subscriber, log_filter) = telemetry::get_subscriber(
+        "actix-template".into(),
+        configuration.telemetry.filter.clone(),
+        configuration.telemetry.format,
+        redaction,
+        writer,
+        tracer,
     );
-    let app_listener = TcpListener::bind(address)?;
-    // Start HTTP server
-    startup::run(app_listener, connection_pool)?.await
+    telemetry::init_subscriber(subscriber);
+
+    // Build and start HTTP server
+    let environment = configuration.environment.as_str().to_string();
+    let application = Application::build(configuration, log_filter.clone()).await?;
+    // Apply configuration changes on file changes or SIGHUP
+    reload::watch(reload::Reloader::new(
+        configuration::configuration_directory(),
+        environment,
+        sources,
+        application.settings_handle(),
+        log_filter,
+    ))
+    .map_err(Error::other)?;
+    application
+        .server_handle()
+        .register_shutdown_hook("flush telemetry", || async {
+            let _ = std::io::stdout().flush();
+            let _ = actix_web::rt::task::spawn_blocking(telemetry::shutdown_tracer).await;
+        });
+    application.run_until_stopped().await
 }
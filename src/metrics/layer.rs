@@ -0,0 +1,44 @@
+use std::time::Instant;
+
+use tracing::span::{Attributes, Id};
+use tracing::Subscriber;
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+use super::{METRICS, REPOSITORY_SPAN_SUFFIX};
+
+// Records how long every repository span was open
+pub struct QueryMetricsLayer;
+
+struct SpanStartedAt(Instant);
+
+impl<S> Layer<S> for QueryMetricsLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        if !attrs.metadata().name().ends_with(REPOSITORY_SPAN_SUFFIX) {
+            return;
+        }
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(SpanStartedAt(Instant::now()));
+        }
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        let elapsed = span
+            .extensions()
+            .get::<SpanStartedAt>()
+            .map(|started_at| started_at.0.elapsed());
+        if let Some(elapsed) = elapsed {
+            METRICS
+                .db_query_duration_seconds
+                .with_label_values(&[span.name()])
+                .observe(elapsed.as_secs_f64());
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use std::future::{ready, Ready};
+use std::time::Instant;
+
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::Error;
+use futures_util::future::LocalBoxFuture;
+
+use super::METRICS;
+
+// Route label for requests that did not match any route, keeps label cardinality bounded
+const UNMATCHED_ROUTE: &str = "unmatched";
+
+// Counts requests and records their latency by method, route pattern and status
+#[derive(Default)]
+pub struct RequestMetrics;
+
+impl<S, B> Transform<S, ServiceRequest> for RequestMetrics
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = RequestMetricsMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequestMetricsMiddleware { service }))
+    }
+}
+
+pub struct RequestMetricsMiddleware<S> {
+    service: S,
+}
+
+impl<S, B> Service<ServiceRequest> for RequestMetricsMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let started_at = Instant::now();
+        let method = req.method().to_string();
+        let future = self.service.call(req);
+        Box::pin(async move {
+            let response = future.await?;
+            let route = response
+                .request()
+                .match_pattern()
+                .unwrap_or_else(|| UNMATCHED_ROUTE.into());
+            let status = response.status().as_u16().to_string();
+            let labels = [method.as_str(), route.as_str(), status.as_str()];
+            METRICS.http_requests_total.with_label_values(&labels).inc();
+            METRICS
+                .http_request_duration_seconds
+                .with_label_values(&labels)
+                .observe(started_at.elapsed().as_secs_f64());
+            Ok(response)
+        })
+    }
+}
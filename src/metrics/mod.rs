@@ -0,0 +1,111 @@
+use once_cell::sync::Lazy;
+use prometheus::{
+    Encoder, Histogram, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, Opts, Registry,
+    TextEncoder,
+};
+
+mod layer;
+mod middleware;
+mod pool;
+
+pub use layer::QueryMetricsLayer;
+pub use middleware::RequestMetrics;
+pub use pool::ConnectionPool;
+
+// Spans with this suffix are repository functions, their durations are recorded per name
+pub const REPOSITORY_SPAN_SUFFIX: &str = " In Database";
+
+// Metrics live for the whole process, like the tracing subscriber feeding them
+pub struct Metrics {
+    registry: Registry,
+    pub http_requests_total: IntCounterVec,
+    pub http_request_duration_seconds: HistogramVec,
+    pub db_pool_connections: IntGauge,
+    pub db_pool_idle_connections: IntGauge,
+    pub db_pool_acquire_duration_seconds: Histogram,
+    pub db_query_duration_seconds: HistogramVec,
+}
+
+pub static METRICS: Lazy<Metrics> = Lazy::new(|| {
+    let registry = Registry::new();
+    let http_requests_total = IntCounterVec::new(
+        Opts::new("http_requests_total", "Number of HTTP requests handled"),
+        &["method", "route", "status"],
+    )
+    .expect("Failed to create metric.");
+    let http_request_duration_seconds = HistogramVec::new(
+        HistogramOpts::new(
+            "http_request_duration_seconds",
+            "HTTP request latency in seconds",
+        ),
+        &["method", "route", "status"],
+    )
+    .expect("Failed to create metric.");
+    let db_pool_connections = IntGauge::new(
+        "db_pool_connections",
+        "Connections currently held by the database pool",
+    )
+    .expect("Failed to create metric.");
+    let db_pool_idle_connections = IntGauge::new(
+        "db_pool_idle_connections",
+        "Idle connections in the database pool",
+    )
+    .expect("Failed to create metric.");
+    let db_pool_acquire_duration_seconds = Histogram::with_opts(HistogramOpts::new(
+        "db_pool_acquire_duration_seconds",
+        "Time requests waited to acquire a database connection",
+    ))
+    .expect("Failed to create metric.");
+    let db_query_duration_seconds = HistogramVec::new(
+        HistogramOpts::new(
+            "db_query_duration_seconds",
+            "Duration of repository functions in seconds",
+        ),
+        &["query"],
+    )
+    .expect("Failed to create metric.");
+
+    registry
+        .register(Box::new(http_requests_total.clone()))
+        .expect("Failed to register metric.");
+    registry
+        .register(Box::new(http_request_duration_seconds.clone()))
+        .expect("Failed to register metric.");
+    registry
+        .register(Box::new(db_pool_connections.clone()))
+        .expect("Failed to register metric.");
+    registry
+        .register(Box::new(db_pool_idle_connections.clone()))
+        .expect("Failed to register metric.");
+    registry
+        .register(Box::new(db_pool_acquire_duration_seconds.clone()))
+        .expect("Failed to register metric.");
+    registry
+        .register(Box::new(db_query_duration_seconds.clone()))
+        .expect("Failed to register metric.");
+
+    Metrics {
+        registry,
+        http_requests_total,
+        http_request_duration_seconds,
+        db_pool_connections,
+        db_pool_idle_connections,
+        db_pool_acquire_duration_seconds,
+        db_query_duration_seconds,
+    }
+});
+
+impl Metrics {
+    // Refresh the pool gauges
+    pub fn observe_pool(&self, db_pool: &ConnectionPool) {
+        self.db_pool_connections.set(db_pool.size().into());
+        self.db_pool_idle_connections.set(db_pool.num_idle() as i64);
+    }
+
+    // Render every metric in the Prometheus text format
+    pub fn render(&self) -> Result<String, prometheus::Error> {
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer)?;
+        Ok(String::from_utf8_lossy(&buffer).into_owned())
+    }
+}
@@ -0,0 +1,62 @@
+use std::time::Instant;
+
+use futures_util::future::BoxFuture;
+use sqlx::pool::PoolConnection;
+use sqlx::{Acquire, PgPool, Postgres, Transaction};
+
+use super::METRICS;
+
+// Database pool timing every acquire, sqlx does not report how long callers wait.
+// It is deliberately not an executor, queries run on a connection acquired from it
+#[derive(Debug, Clone)]
+pub struct ConnectionPool(PgPool);
+
+impl From<PgPool> for ConnectionPool {
+    fn from(db_pool: PgPool) -> Self {
+        Self(db_pool)
+    }
+}
+
+impl ConnectionPool {
+    pub async fn acquire(&self) -> Result<PoolConnection<Postgres>, sqlx::Error> {
+        let started_at = Instant::now();
+        let connection = self.0.acquire().await;
+        METRICS
+            .db_pool_acquire_duration_seconds
+            .observe(started_at.elapsed().as_secs_f64());
+        connection
+    }
+
+    pub async fn begin(&self) -> Result<Transaction<'static, Postgres>, sqlx::Error> {
+        Transaction::begin(self.acquire().await?).await
+    }
+
+    // Connections currently open, idle or in use
+    pub fn size(&self) -> u32 {
+        self.0.size()
+    }
+
+    pub fn num_idle(&self) -> usize {
+        self.0.num_idle()
+    }
+
+    pub async fn close(&self) {
+        self.0.close().await
+    }
+}
+
+// Lets sqlx APIs such as the migrator take the pool directly
+impl<'a> Acquire<'a> for &'_ ConnectionPool {
+    type Database = Postgres;
+    type Connection = PoolConnection<Postgres>;
+
+    fn acquire(self) -> BoxFuture<'static, Result<Self::Connection, sqlx::Error>> {
+        let db_pool = self.clone();
+        Box::pin(async move { db_pool.acquire().await })
+    }
+
+    fn begin(self) -> BoxFuture<'static, Result<Transaction<'a, Postgres>, sqlx::Error>> {
+        let db_pool = self.clone();
+        Box::pin(async move { Transaction::begin(db_pool.acquire().await?).await })
+    }
+}
@@ -0,0 +1,253 @@
+use std::fmt;
+use std::path::Path;
+
+use sqlx::migrate::{MigrateError, Migrator};
+use sqlx::{Acquire, Column, Connection, Describe, Either, Executor, PgConnection, Postgres};
+
+use crate::DatabaseSettings;
+
+// Migrations embedded in the binary at compile time
+pub static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
+// Held while migrating at startup so replicas starting together take turns
+const MIGRATION_LOCK_KEY: i64 = 0x6163_7469_785f_6d67;
+
+#[derive(Debug)]
+pub enum MigrationError {
+    Database(sqlx::Error),
+    Migrate(MigrateError),
+    // Applied by a newer release of the binary
+    NewerThanBinary(Vec<i64>),
+}
+
+impl fmt::Display for MigrationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MigrationError::Database(e) => write!(f, "Failed to migrate the database: {}", e),
+            MigrationError::Migrate(e) => write!(f, "Failed to migrate the database: {}", e),
+            MigrationError::NewerThanBinary(versions) => write!(
+                f,
+                "The database has migrations this binary does not know about: {}",
+                versions
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MigrationError {}
+
+impl From<sqlx::Error> for MigrationError {
+    fn from(e: sqlx::Error) -> Self {
+        MigrationError::Database(e)
+    }
+}
+
+impl From<MigrateError> for MigrationError {
+    fn from(e: MigrateError) -> Self {
+        MigrationError::Migrate(e)
+    }
+}
+
+// Apply pending migrations under an advisory lock, refusing databases migrated by a newer binary
+#[tracing::instrument(name = "Run Migrations", skip(configuration))]
+pub async fn run_migrations(configuration: &DatabaseSettings) -> Result<(), MigrationError> {
+    // A connection of its own, closing it releases the session lock on every path
+    let mut connection = PgConnection::connect_with(&configuration.with_database()).await?;
+    sqlx::query("SELECT pg_advisory_lock($1)")
+        .bind(MIGRATION_LOCK_KEY)
+        .execute(&mut connection)
+        .await?;
+    let result = migrate_locked(&mut connection).await;
+    connection.close().await?;
+    result
+}
+
+async fn migrate_locked(connection: &mut PgConnection) -> Result<(), MigrationError> {
+    let unknown = unknown_migrations(&applied_versions(connection).await?);
+    if !unknown.is_empty() {
+        return Err(MigrationError::NewerThanBinary(unknown));
+    }
+    MIGRATOR.run(connection).await?;
+    Ok(())
+}
+
+// Applied versions missing from the binary
+pub fn unknown_migrations(applied: &[i64]) -> Vec<i64> {
+    applied
+        .iter()
+        .copied()
+        .filter(|version| {
+            MIGRATOR
+                .iter()
+                .all(|migration| migration.version != *version)
+        })
+        .collect()
+}
+
+// Versions successfully applied to the database, empty before the first migration
+pub async fn applied_migrations<'a>(
+    db_pool: impl Acquire<'a, Database = Postgres>,
+) -> Result<Vec<i64>, sqlx::Error> {
+    let mut connection = db_pool.acquire().await?;
+    applied_versions(&mut connection).await
+}
+
+async fn applied_versions(connection: &mut PgConnection) -> Result<Vec<i64>, sqlx::Error> {
+    let table_exists: bool =
+        sqlx::query_scalar("SELECT to_regclass('_sqlx_migrations') IS NOT NULL")
+            .fetch_one(&mut *connection)
+            .await?;
+    if !table_exists {
+        return Ok(Vec::new());
+    }
+    sqlx::query_scalar("SELECT version FROM _sqlx_migrations WHERE success ORDER BY version")
+        .fetch_all(&mut *connection)
+        .await
+}
+
+// Versions known to the binary but not applied to the database
+pub async fn pending_migrations<'a>(
+    db_pool: impl Acquire<'a, Database = Postgres>,
+) -> Result<Vec<i64>, sqlx::Error> {
+    let applied = applied_migrations(db_pool).await?;
+    Ok(MIGRATOR
+        .iter()
+        .filter(|migration| migration.migration_type.is_up_migration())
+        .map(|migration| migration.version)
+        .filter(|version| !applied.contains(version))
+        .collect())
+}
+
+// Create the configured database unless it exists, returning whether it was created
+pub async fn create_database_if_missing(
+    configuration: &DatabaseSettings,
+) -> Result<bool, sqlx::Error> {
+    let mut connection = PgConnection::connect_with(&configuration.without_database()).await?;
+    let exists: bool =
+        sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM pg_database WHERE datname = $1)")
+            .bind(&configuration.database_name)
+            .fetch_one(&mut connection)
+            .await?;
+    if !exists {
+        let name = configuration.database_name.replace('"', "\"\"");
+        connection
+            .execute(format!(r#"CREATE DATABASE "{}";"#, name).as_str())
+            .await?;
+    }
+    connection.close().await?;
+    Ok(!exists)
+}
+
+// A query from the offline query data the live schema no longer describes the same way
+#[derive(Debug)]
+pub struct SchemaDrift {
+    pub file: String,
+    pub query: String,
+    pub reason: String,
+}
+
+impl fmt::Display for SchemaDrift {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}\n  {}", self.file, self.reason, self.query.trim())
+    }
+}
+
+// Describe every query in `query_data` (the .sqlx directory) against the database
+// and report those whose columns, parameters or nullability differ
+#[tracing::instrument(name = "Check Schema Drift", skip(configuration))]
+pub async fn schema_drift(
+    configuration: &DatabaseSettings,
+    query_data: &Path,
+) -> Result<Vec<SchemaDrift>, std::io::Error> {
+    let mut files = std::fs::read_dir(query_data)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    files.retain(|path| {
+        path.extension()
+            .is_some_and(|extension| extension == "json")
+    });
+    files.sort();
+
+    // A fresh connection, pooled ones answer describe from statements cached
+    // before the schema changed
+    let mut connection = PgConnection::connect_with(&configuration.with_database())
+        .await
+        .map_err(std::io::Error::other)?;
+    let mut drift = Vec::new();
+    for path in files {
+        let data: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&path)?)?;
+        let file = path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned();
+        let Some(query) = data["query"].as_str() else {
+            return Err(std::io::Error::other(format!("{} has no query", file)));
+        };
+        let reason = match connection.describe(query).await {
+            Ok(describe) => {
+                let actual = query_data_describe(&describe);
+                ["columns", "parameters", "nullable"]
+                    .into_iter()
+                    .filter(|field| data["describe"][field] != actual[field])
+                    .map(|field| {
+                        format!(
+                            "{} expected {}, found {}",
+                            field, data["describe"][field], actual[field]
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join("; ")
+            }
+            Err(e) => e.to_string(),
+        };
+        if !reason.is_empty() {
+            drift.push(SchemaDrift {
+                file,
+                query: query.to_string(),
+                reason,
+            });
+        }
+    }
+    connection.close().await.map_err(std::io::Error::other)?;
+    Ok(drift)
+}
+
+// The shape `cargo sqlx prepare` writes, types named like the PgType variants
+fn query_data_describe(describe: &Describe<Postgres>) -> serde_json::Value {
+    let type_name = |type_info: &sqlx::postgres::PgTypeInfo| {
+        let name = format!("{:?}", type_info);
+        serde_json::Value::from(
+            name.strip_prefix("PgTypeInfo(")
+                .and_then(|name| name.strip_suffix(')'))
+                .unwrap_or(&name),
+        )
+    };
+    let columns: Vec<_> = describe
+        .columns
+        .iter()
+        .map(|column| {
+            serde_json::json!({
+                "ordinal": column.ordinal(),
+                "name": column.name(),
+                "type_info": type_name(column.type_info()),
+            })
+        })
+        .collect();
+    let parameters = match &describe.parameters {
+        Some(Either::Left(types)) => {
+            serde_json::json!({ "Left": types.iter().map(type_name).collect::<Vec<_>>() })
+        }
+        Some(Either::Right(count)) => serde_json::json!({ "Right": count }),
+        None => serde_json::Value::Null,
+    };
+    serde_json::json!({
+        "columns": columns,
+        "parameters": parameters,
+        "nullable": describe.nullable,
+    })
+}
@@ -0,0 +1,166 @@
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use arc_swap::ArcSwap;
+use notify::{RecursiveMode, Watcher};
+use tokio::sync::mpsc;
+
+use crate::configuration::{
+    load_configuration_with_sources, ConfigurationError, Settings, ValueSources,
+};
+use crate::telemetry::LogFilter;
+
+// Settings applied without a restart, everything else is fixed at startup
+pub const RELOADABLE_KEYS: [&str; 3] = [
+    "application.base_url",
+    "storage.max_avatar_size",
+    "telemetry.filter",
+];
+
+// Wait for editors to finish writing before reloading
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+// Current settings, read by handlers through web::Data
+#[derive(Clone)]
+pub struct SettingsHandle(Arc<ArcSwap<Settings>>);
+
+impl SettingsHandle {
+    pub fn new(settings: Settings) -> Self {
+        Self(Arc::new(ArcSwap::from_pointee(settings)))
+    }
+
+    pub fn load(&self) -> Arc<Settings> {
+        self.0.load_full()
+    }
+
+    pub fn store(&self, settings: Settings) {
+        self.0.store(Arc::new(settings))
+    }
+}
+
+// Keys changed by a reload
+#[derive(Debug, Default)]
+pub struct ReloadOutcome {
+    pub applied: Vec<String>,
+    // Need a restart, the running values are kept
+    pub rejected: Vec<String>,
+}
+
+pub struct Reloader {
+    directory: PathBuf,
+    environment: String,
+    // Values of the last successful load, each reload reports what changed since
+    sources: ValueSources,
+    settings: SettingsHandle,
+    log_filter: LogFilter,
+}
+
+impl Reloader {
+    // `sources` are those the settings were loaded from at startup
+    pub fn new(
+        directory: PathBuf,
+        environment: String,
+        sources: ValueSources,
+        settings: SettingsHandle,
+        log_filter: LogFilter,
+    ) -> Self {
+        Self {
+            directory,
+            environment,
+            sources,
+            settings,
+            log_filter,
+        }
+    }
+
+    // Load and validate the configuration again and publish the reloadable changes
+    #[tracing::instrument(name = "Reload Configuration", skip(self))]
+    pub fn reload(&mut self) -> Result<ReloadOutcome, ConfigurationError> {
+        let (new_settings, new_sources) =
+            load_configuration_with_sources(&self.directory, &self.environment)?;
+
+        let mut outcome = ReloadOutcome::default();
+        let current_values = self.sources.values();
+        let new_values = new_sources.values();
+        let keys = current_values.keys().chain(new_values.keys());
+        for key in keys.collect::<BTreeSet<_>>() {
+            let current = current_values.get(key).map(ToString::to_string);
+            let new = new_values.get(key).map(ToString::to_string);
+            if current == new {
+                continue;
+            }
+            if RELOADABLE_KEYS.contains(&key.as_str()) {
+                outcome.applied.push(key.clone());
+            } else {
+                outcome.rejected.push(key.clone());
+            }
+        }
+
+        // Copy only the keys listed in RELOADABLE_KEYS
+        let mut settings = Settings::clone(&self.settings.load());
+        settings.application.base_url = new_settings.application.base_url;
+        settings.storage.max_avatar_size = new_settings.storage.max_avatar_size;
+        if settings.telemetry.filter != new_settings.telemetry.filter {
+            // Validation already parsed the directives
+            if let Err(e) = self.log_filter.set(&new_settings.telemetry.filter) {
+                tracing::error!(error = %e, "Failed to apply the reloaded log filter");
+            }
+            settings.telemetry.filter = new_settings.telemetry.filter;
+        }
+        self.settings.store(settings);
+        self.sources = new_sources;
+
+        if !outcome.rejected.is_empty() {
+            tracing::warn!(
+                keys = %outcome.rejected.join(", "),
+                "Configuration changes require a restart and were not applied"
+            );
+        }
+        if !outcome.applied.is_empty() {
+            tracing::info!(keys = %outcome.applied.join(", "), "Configuration reloaded");
+        }
+        Ok(outcome)
+    }
+}
+
+// Reload when the configuration directory changes or on SIGHUP
+pub fn watch(mut reloader: Reloader) -> notify::Result<()> {
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+    let file_changes = sender.clone();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        if event.is_ok_and(|event| !event.kind.is_access()) {
+            let _ = file_changes.send(());
+        }
+    })?;
+    watcher.watch(&reloader.directory, RecursiveMode::NonRecursive)?;
+
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        let mut hangup = signal(SignalKind::hangup())?;
+        actix_web::rt::spawn(async move {
+            while hangup.recv().await.is_some() {
+                if sender.send(()).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+    #[cfg(not(unix))]
+    drop(sender);
+
+    actix_web::rt::spawn(async move {
+        // Dropping the watcher stops it
+        let _watcher = watcher;
+        while receiver.recv().await.is_some() {
+            actix_web::rt::time::sleep(DEBOUNCE).await;
+            while receiver.try_recv().is_ok() {}
+            if let Err(e) = reloader.reload() {
+                tracing::error!(error = %e, "Invalid configuration, keeping the current settings");
+            }
+        }
+    });
+    Ok(())
+}
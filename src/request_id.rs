@@ -0,0 +1,169 @@
+use std::future::{ready, Ready};
+
+use actix_web::body::{to_bytes, BoxBody, MessageBody};
+use actix_web::dev::{forward_ready, Payload, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header::{self, HeaderName, HeaderValue};
+use actix_web::{Error, FromRequest, HttpMessage, HttpRequest};
+use futures_util::future::LocalBoxFuture;
+use tracing::Span;
+use tracing_actix_web::{DefaultRootSpanBuilder, RootSpanBuilder};
+use uuid::Uuid;
+
+pub const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+pub const REQUEST_ID_MAX_LENGTH: usize = 128;
+
+// Identifies a request across our logs, responses and the services we call
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestId(String);
+
+impl RequestId {
+    pub fn generate() -> Self {
+        Self(Uuid::new_v4().to_string())
+    }
+
+    // Incoming ids end up in logs and headers, only accept short plain tokens
+    pub fn parse(value: &str) -> Option<Self> {
+        let is_valid = (1..=REQUEST_ID_MAX_LENGTH).contains(&value.len())
+            && value
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+        is_valid.then(|| Self(value.to_owned()))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for RequestId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+// Falls back to a fresh id when the middleware is not registered
+impl FromRequest for RequestId {
+    type Error = Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let request_id = req.extensions().get::<RequestId>().cloned();
+        ready(Ok(request_id.unwrap_or_else(RequestId::generate)))
+    }
+}
+
+// Root span carrying our request id instead of the one generated by TracingLogger
+pub struct RequestIdRootSpanBuilder;
+
+impl RootSpanBuilder for RequestIdRootSpanBuilder {
+    fn on_request_start(request: &ServiceRequest) -> Span {
+        let span = tracing_actix_web::root_span!(request);
+        if let Some(request_id) = request.extensions().get::<RequestId>() {
+            span.record("request_id", request_id.as_str());
+        }
+        span
+    }
+
+    fn on_request_end<B: MessageBody>(span: Span, outcome: &Result<ServiceResponse<B>, Error>) {
+        DefaultRootSpanBuilder::on_request_end(span, outcome);
+    }
+}
+
+// Assigns the request id, echoes it on every response and adds it to error bodies.
+// Must wrap TracingLogger so the id exists when the root span is created.
+#[derive(Default)]
+pub struct RequestIdentity;
+
+impl<S, B> Transform<S, ServiceRequest> for RequestIdentity
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Transform = RequestIdentityMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequestIdentityMiddleware { service }))
+    }
+}
+
+pub struct RequestIdentityMiddleware<S> {
+    service: S,
+}
+
+impl<S, B> Service<ServiceRequest> for RequestIdentityMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let request_id = req
+            .headers()
+            .get(REQUEST_ID_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(RequestId::parse)
+            .unwrap_or_else(RequestId::generate);
+        req.extensions_mut().insert(request_id.clone());
+        let future = self.service.call(req);
+        Box::pin(async move {
+            // Handler errors already arrive here as responses
+            let response = future.await?.map_into_boxed_body();
+            let mut response =
+                if response.status().is_client_error() || response.status().is_server_error() {
+                    add_request_id_to_body(response, &request_id).await?
+                } else {
+                    response
+                };
+            response.headers_mut().insert(
+                REQUEST_ID_HEADER,
+                HeaderValue::from_str(request_id.as_str())
+                    .expect("Request ids are valid header values"),
+            );
+            Ok(response)
+        })
+    }
+}
+
+// JSON objects get a request_id field, anything else gets the id appended
+async fn add_request_id_to_body(
+    response: ServiceResponse<BoxBody>,
+    request_id: &RequestId,
+) -> Result<ServiceResponse<BoxBody>, Error> {
+    let (http_request, response) = response.into_parts();
+    let (response, body) = response.into_parts();
+    let body = to_bytes(body)
+        .await
+        .map_err(|e| actix_web::error::ErrorInternalServerError(e.to_string()))?;
+    let is_json = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|content_type| content_type.starts_with("application/json"));
+    let body = match serde_json::from_slice::<serde_json::Value>(&body) {
+        Ok(serde_json::Value::Object(mut object)) if is_json => {
+            object.insert("request_id".into(), request_id.as_str().into());
+            serde_json::to_vec(&object)?
+        }
+        _ if body.is_empty() => format!("request id: {}", request_id).into_bytes(),
+        _ => {
+            let mut body = body.to_vec();
+            body.extend_from_slice(format!(" (request id: {})", request_id).as_bytes());
+            body
+        }
+    };
+    Ok(ServiceResponse::new(
+        http_request,
+        response.set_body(BoxBody::new(body)),
+    ))
+}
@@ -0,0 +1,79 @@
+use actix_web::{put, web, HttpResponse};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::authentication::AdminUser;
+use crate::telemetry::{LogFilter, LogFilterError};
+
+// Change the log filter via PUT
+#[derive(Debug, Deserialize)]
+pub struct UpdateLogLevel {
+    // EnvFilter directives, e.g. "info,actix_template=debug"
+    directives: String,
+    // Restore the previous directives after this many minutes
+    revert_after_minutes: Option<u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LogLevel {
+    pub directives: String,
+    pub previous: Option<String>,
+    pub revert_at: Option<DateTime<Utc>>,
+}
+
+#[put("/log-level")]
+#[tracing::instrument(name = "Update Log Level", skip(log_filter), fields(admin_id = %admin.id))]
+async fn update_log_level(
+    admin: AdminUser,
+    json: web::Json<UpdateLogLevel>,
+    log_filter: web::Data<LogFilter>,
+) -> HttpResponse {
+    let previous = log_filter.current();
+    // Nothing to restore when the current directives cannot be read
+    if json.revert_after_minutes.is_some() && previous.is_none() {
+        return HttpResponse::Conflict()
+            .body("The current log level is unknown and could not be reverted");
+    }
+    let generation = match log_filter.set(&json.directives) {
+        Ok(generation) => generation,
+        Err(e @ LogFilterError::InvalidDirectives(_)) => {
+            return HttpResponse::BadRequest().body(e.to_string())
+        }
+        Err(LogFilterError::Reload(_)) => {
+            return HttpResponse::InternalServerError().body("Internal Server Error")
+        }
+    };
+    tracing::warn!(
+        admin_id = %admin.id,
+        directives = %json.directives,
+        previous = ?previous,
+        changed_at = %Utc::now(),
+        "Log level changed"
+    );
+
+    let revert_at = match (json.revert_after_minutes, previous.clone()) {
+        (Some(minutes), Some(previous)) => {
+            let log_filter = log_filter.clone();
+            actix_web::rt::spawn(async move {
+                tokio::time::sleep(std::time::Duration::from_secs(u64::from(minutes) * 60)).await;
+                match log_filter.revert(&previous, generation) {
+                    Ok(true) => tracing::warn!(directives = %previous, "Log level reverted"),
+                    // Changed again in the meantime, the newer change wins
+                    Ok(false) => {}
+                    Err(e) => tracing::error!(error = %e, "Failed to revert log level"),
+                }
+            });
+            Some(Utc::now() + Duration::minutes(minutes.into()))
+        }
+        _ => None,
+    };
+    HttpResponse::Ok().json(LogLevel {
+        directives: json.into_inner().directives,
+        previous,
+        revert_at,
+    })
+}
+
+pub fn init_admin_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::scope("/admin").service(update_log_level));
+}
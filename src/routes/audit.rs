@@ -0,0 +1,80 @@
+use actix_web::{get, web, HttpResponse};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::authentication::AdminUser;
+use crate::metrics::ConnectionPool;
+
+const DEFAULT_AUDIT_EVENTS_LIMIT: i64 = 100;
+const MAX_AUDIT_EVENTS_LIMIT: i64 = 1000;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AuditEvent {
+    pub id: Uuid,
+    pub occurred_at: chrono::DateTime<Utc>,
+    pub actor_id: Option<Uuid>,
+    pub action: String,
+    pub target_id: Uuid,
+    pub changed_fields: Vec<String>,
+    pub request_id: Option<String>,
+    pub ip_address: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AuditEventsQuery {
+    target: Option<Uuid>,
+    actor: Option<Uuid>,
+    from: Option<chrono::DateTime<Utc>>,
+    to: Option<chrono::DateTime<Utc>>,
+    limit: Option<i64>,
+}
+
+// Get audit events via GET, admins only
+#[get("")]
+#[tracing::instrument(name = "Get Audit Events", skip(db_pool), fields(admin_id = %admin.id))]
+async fn get_audit_events(
+    admin: AdminUser,
+    query: web::Query<AuditEventsQuery>,
+    db_pool: web::Data<ConnectionPool>,
+) -> HttpResponse {
+    let result = get_audit_events_repository(&query, &db_pool).await;
+    match result {
+        Ok(events) => HttpResponse::Ok().json(events),
+        Err(_) => HttpResponse::InternalServerError().body("Internal Server Error"),
+    }
+}
+
+#[tracing::instrument(name = "Get Audit Events In Database", skip(db_pool))]
+async fn get_audit_events_repository(
+    query: &AuditEventsQuery,
+    db_pool: &ConnectionPool,
+) -> Result<Vec<AuditEvent>, sqlx::Error> {
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_AUDIT_EVENTS_LIMIT)
+        .clamp(1, MAX_AUDIT_EVENTS_LIMIT);
+    sqlx::query_as!(
+        AuditEvent,
+        r#"
+        SELECT * FROM audit_events
+        WHERE ($1::uuid IS NULL OR target_id = $1)
+        AND ($2::uuid IS NULL OR actor_id = $2)
+        AND ($3::timestamptz IS NULL OR occurred_at >= $3)
+        AND ($4::timestamptz IS NULL OR occurred_at <= $4)
+        ORDER BY occurred_at DESC
+        LIMIT $5
+        "#,
+        query.target,
+        query.actor,
+        query.from,
+        query.to,
+        limit
+    )
+    .fetch_all(&mut *db_pool.acquire().await?)
+    .await
+}
+
+pub fn init_audit_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::scope("/audit").service(get_audit_events));
+}
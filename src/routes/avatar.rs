@@ -0,0 +1,264 @@
+use std::io::Cursor;
+
+use actix_multipart::Multipart;
+use actix_web::{get, put, web, HttpResponse};
+use futures_util::TryStreamExt;
+use image::{imageops::FilterType, ImageFormat, ImageOutputFormat};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::audit::{insert_audit_event, AuditAction, AuditContext};
+use crate::authentication::AuthenticatedUser;
+use crate::metrics::ConnectionPool;
+use crate::reload::SettingsHandle;
+use crate::storage::BlobStore;
+
+// Square thumbnail edge lengths generated for every avatar
+pub const AVATAR_THUMBNAIL_SIZES: [u32; 2] = [64, 256];
+
+// Name of the multipart field carrying the image
+const AVATAR_FIELD_NAME: &str = "avatar";
+
+// Detect the image format from its magic bytes rather than the client supplied type
+pub fn detect_image_format(bytes: &[u8]) -> Option<ImageFormat> {
+    if bytes.starts_with(&[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A]) {
+        Some(ImageFormat::Png)
+    } else if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some(ImageFormat::Jpeg)
+    } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+        Some(ImageFormat::Gif)
+    } else if bytes.len() >= 12 && &bytes[0..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        Some(ImageFormat::WebP)
+    } else {
+        None
+    }
+}
+
+fn content_type(format: ImageFormat) -> &'static str {
+    match format {
+        ImageFormat::Png => "image/png",
+        ImageFormat::Jpeg => "image/jpeg",
+        ImageFormat::Gif => "image/gif",
+        ImageFormat::WebP => "image/webp",
+        _ => "application/octet-stream",
+    }
+}
+
+fn original_key(id: Uuid) -> String {
+    format!("avatars/{}/original", id)
+}
+
+fn thumbnail_key(id: Uuid, size: u32) -> String {
+    format!("avatars/{}/{}.png", id, size)
+}
+
+enum AvatarUploadError {
+    Missing,
+    TooLarge,
+    Multipart,
+}
+
+// Read the avatar field while enforcing the size limit
+async fn read_avatar_field(
+    payload: &mut Multipart,
+    max_size: usize,
+) -> Result<Vec<u8>, AvatarUploadError> {
+    while let Some(mut field) = payload
+        .try_next()
+        .await
+        .map_err(|_| AvatarUploadError::Multipart)?
+    {
+        if field.name() != AVATAR_FIELD_NAME {
+            continue;
+        }
+        let mut bytes = Vec::new();
+        while let Some(chunk) = field
+            .try_next()
+            .await
+            .map_err(|_| AvatarUploadError::Multipart)?
+        {
+            if bytes.len() + chunk.len() > max_size {
+                return Err(AvatarUploadError::TooLarge);
+            }
+            bytes.extend_from_slice(&chunk);
+        }
+        return Ok(bytes);
+    }
+    Err(AvatarUploadError::Missing)
+}
+
+// Decode the image and render every thumbnail size as PNG
+fn generate_thumbnails(
+    bytes: &[u8],
+    format: ImageFormat,
+) -> Result<Vec<(u32, Vec<u8>)>, image::ImageError> {
+    let image = image::load_from_memory_with_format(bytes, format)?;
+    AVATAR_THUMBNAIL_SIZES
+        .iter()
+        .map(|size| {
+            let thumbnail = image.resize_to_fill(*size, *size, FilterType::Lanczos3);
+            let mut encoded = Cursor::new(Vec::new());
+            thumbnail.write_to(&mut encoded, ImageOutputFormat::Png)?;
+            Ok((*size, encoded.into_inner()))
+        })
+        .collect()
+}
+
+// Upload an avatar via multipart PUT
+#[put("/{id}/avatar")]
+#[tracing::instrument(
+    name = "Upload Avatar",
+    skip(id, user, audit, payload, db_pool, blob_store, settings),
+    fields(id = %id)
+)]
+pub async fn upload_avatar(
+    id: web::Path<Uuid>,
+    user: AuthenticatedUser,
+    audit: AuditContext,
+    mut payload: Multipart,
+    db_pool: web::Data<ConnectionPool>,
+    blob_store: web::Data<dyn BlobStore>,
+    settings: web::Data<SettingsHandle>,
+) -> HttpResponse {
+    let id = *id;
+    if !user.can_manage(id) {
+        return HttpResponse::Forbidden().body("Forbidden");
+    }
+    // Check if user exists before accepting the upload
+    match check_if_user_id_exists(id, &db_pool).await {
+        Ok(true) => {}
+        Ok(false) => return HttpResponse::NotFound().body("User not found"),
+        Err(_) => return HttpResponse::InternalServerError().body("Internal Server Error"),
+    }
+
+    let bytes = match read_avatar_field(&mut payload, settings.load().storage.max_avatar_size).await
+    {
+        Ok(bytes) => bytes,
+        Err(AvatarUploadError::Missing) => {
+            return HttpResponse::BadRequest().body("Missing avatar field")
+        }
+        // Close the connection rather than draining the rest of the body
+        Err(AvatarUploadError::TooLarge) => {
+            return HttpResponse::PayloadTooLarge()
+                .force_close()
+                .body("Avatar is too large")
+        }
+        Err(AvatarUploadError::Multipart) => {
+            return HttpResponse::BadRequest().body("Invalid multipart payload")
+        }
+    };
+    let format = match detect_image_format(&bytes) {
+        Some(format) => format,
+        None => return HttpResponse::UnsupportedMediaType().body("Unsupported image type"),
+    };
+
+    // Decoding and resizing is CPU bound, keep it off the async workers
+    let bytes = web::Bytes::from(bytes);
+    let source = bytes.clone();
+    let thumbnails = match web::block(move || generate_thumbnails(&source, format)).await {
+        Ok(Ok(thumbnails)) => thumbnails,
+        Ok(Err(_)) => return HttpResponse::UnsupportedMediaType().body("Invalid image"),
+        Err(_) => return HttpResponse::InternalServerError().body("Internal Server Error"),
+    };
+
+    // Store original and thumbnails
+    if blob_store
+        .put(&original_key(id), bytes, content_type(format))
+        .await
+        .is_err()
+    {
+        return HttpResponse::InternalServerError().body("Internal Server Error");
+    }
+    for (size, thumbnail) in thumbnails {
+        if blob_store
+            .put(&thumbnail_key(id, size), thumbnail.into(), "image/png")
+            .await
+            .is_err()
+        {
+            return HttpResponse::InternalServerError().body("Internal Server Error");
+        }
+    }
+
+    let avatar_url = format!("/user/{}/avatar", id);
+    match update_avatar_url_repository(id, &avatar_url, &audit, &db_pool).await {
+        Ok(_) => HttpResponse::Ok().json(avatar_url),
+        Err(sqlx::Error::RowNotFound) => HttpResponse::NotFound().body("User not found"),
+        Err(_) => HttpResponse::InternalServerError().body("Internal Server Error"),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AvatarQuery {
+    // Thumbnail edge length, original image when omitted
+    size: Option<u32>,
+}
+
+// Get an avatar or one of its thumbnails via GET
+#[get("/{id}/avatar")]
+#[tracing::instrument(name = "Get Avatar", skip(id, blob_store), fields(id = %id))]
+pub async fn get_avatar(
+    id: web::Path<Uuid>,
+    query: web::Query<AvatarQuery>,
+    blob_store: web::Data<dyn BlobStore>,
+) -> HttpResponse {
+    let key = match query.size {
+        None => original_key(*id),
+        Some(size) if AVATAR_THUMBNAIL_SIZES.contains(&size) => thumbnail_key(*id, size),
+        Some(_) => return HttpResponse::BadRequest().body("Unsupported thumbnail size"),
+    };
+    match blob_store.get(&key).await {
+        Ok(Some(bytes)) => {
+            let content_type = detect_image_format(&bytes)
+                .map(content_type)
+                .unwrap_or("application/octet-stream");
+            HttpResponse::Ok().content_type(content_type).body(bytes)
+        }
+        Ok(None) => HttpResponse::NotFound().body("Avatar not found"),
+        Err(_) => HttpResponse::InternalServerError().body("Internal Server Error"),
+    }
+}
+
+#[tracing::instrument(name = "Check User Id Exists In Database", skip(db_pool))]
+async fn check_if_user_id_exists(id: Uuid, db_pool: &ConnectionPool) -> Result<bool, sqlx::Error> {
+    let found_user = sqlx::query!(
+        r#"
+        SELECT id FROM users WHERE id = $1
+        "#,
+        id
+    )
+    .fetch_optional(&mut *db_pool.acquire().await?)
+    .await?;
+    Ok(found_user.is_some())
+}
+
+#[tracing::instrument(name = "Update Avatar Url In Database", skip(id, audit, db_pool), fields(id = %id))]
+async fn update_avatar_url_repository(
+    id: Uuid,
+    avatar_url: &str,
+    audit: &AuditContext,
+    db_pool: &ConnectionPool,
+) -> Result<(), sqlx::Error> {
+    let mut transaction = db_pool.begin().await?;
+    let result = sqlx::query!(
+        r#"
+        UPDATE users SET avatar_url = $1 WHERE id = $2
+        "#,
+        avatar_url,
+        id
+    )
+    .execute(&mut *transaction)
+    .await?;
+    if result.rows_affected() == 0 {
+        return Err(sqlx::Error::RowNotFound);
+    }
+    insert_audit_event(
+        &mut transaction,
+        audit,
+        AuditAction::UserUpdated,
+        id,
+        &["avatar_url"],
+    )
+    .await?;
+    transaction.commit().await?;
+    Ok(())
+}
@@ -0,0 +1,266 @@
+use actix_web::{get, post, web, HttpResponse};
+use chrono::{Duration, Utc};
+use rand::{distributions::Alphanumeric, thread_rng, Rng};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::audit::{insert_audit_event, AuditAction, AuditContext};
+use crate::authentication::AuthenticatedUser;
+use crate::email_client::EmailClient;
+use crate::metrics::ConnectionPool;
+use crate::reload::SettingsHandle;
+use crate::request_id::RequestId;
+
+// How long a confirmation link stays valid
+pub const EMAIL_CHANGE_TOKEN_LIFETIME_HOURS: i64 = 24;
+
+// Request an email change via POST
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct ChangeEmail {
+    #[validate(email)]
+    email: String,
+}
+
+#[post("/{id}/email")]
+#[tracing::instrument(
+    name = "Request Email Change",
+    skip(id, json, user, db_pool, email_client, settings),
+    fields(id = %id)
+)]
+pub async fn request_email_change(
+    request_id: RequestId,
+    id: web::Path<Uuid>,
+    json: web::Json<ChangeEmail>,
+    user: AuthenticatedUser,
+    db_pool: web::Data<ConnectionPool>,
+    email_client: web::Data<EmailClient>,
+    settings: web::Data<SettingsHandle>,
+) -> HttpResponse {
+    // Whoever confirms the new address takes over the account
+    if !user.can_manage(*id) {
+        return HttpResponse::Forbidden().body("Forbidden");
+    }
+    if let Err(errors) = json.validate() {
+        return HttpResponse::BadRequest().json(errors);
+    }
+    let new_email = json.into_inner().email;
+    // Fail early when the address is already used, it is checked again on confirmation
+    match check_if_email_taken(&new_email, &db_pool).await {
+        Ok(false) => {}
+        Ok(true) => return HttpResponse::Conflict().body("Email already in use"),
+        Err(_) => return HttpResponse::InternalServerError().body("Internal Server Error"),
+    }
+    let token = generate_email_change_token();
+    match store_email_change_request_repository(*id, &new_email, &token, &db_pool).await {
+        Ok(_) => {}
+        Err(sqlx::Error::RowNotFound) => return HttpResponse::NotFound().body("User not found"),
+        Err(_) => return HttpResponse::InternalServerError().body("Internal Server Error"),
+    }
+    let base_url = &settings.load().application.base_url;
+    if send_confirmation_email(&email_client, &request_id, &new_email, base_url, &token)
+        .await
+        .is_err()
+    {
+        return HttpResponse::InternalServerError().body("Internal Server Error");
+    }
+    HttpResponse::Accepted().body("Confirmation email sent")
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ConfirmEmailChange {
+    token: String,
+}
+
+// Confirm an email change via GET, using the link sent to the new address
+#[get("/email/confirm")]
+#[tracing::instrument(
+    name = "Confirm Email Change",
+    skip(query, audit, db_pool, email_client)
+)]
+pub async fn confirm_email_change(
+    request_id: RequestId,
+    query: web::Query<ConfirmEmailChange>,
+    audit: AuditContext,
+    db_pool: web::Data<ConnectionPool>,
+    email_client: web::Data<EmailClient>,
+) -> HttpResponse {
+    let changed = match confirm_email_change_repository(&query.token, &audit, &db_pool).await {
+        Ok(changed) => changed,
+        Err(sqlx::Error::RowNotFound) => {
+            return HttpResponse::NotFound().body("Invalid or expired token")
+        }
+        // Address was claimed by another user since the request
+        Err(sqlx::Error::Database(e)) if e.code().as_deref() == Some("23505") => {
+            return HttpResponse::Conflict().body("Email already in use")
+        }
+        Err(_) => return HttpResponse::InternalServerError().body("Internal Server Error"),
+    };
+    // The change is done, a failed notification must not report it as failed
+    if let Err(e) = send_change_notification_email(&email_client, &request_id, &changed).await {
+        tracing::error!(error = %e, "Failed to notify the previous email address");
+    }
+    HttpResponse::Ok().body("Email updated")
+}
+
+fn generate_email_change_token() -> String {
+    let mut rng = thread_rng();
+    std::iter::repeat_with(|| rng.sample(Alphanumeric))
+        .map(char::from)
+        .take(32)
+        .collect()
+}
+
+async fn send_confirmation_email(
+    email_client: &EmailClient,
+    request_id: &RequestId,
+    new_email: &str,
+    base_url: &str,
+    token: &str,
+) -> Result<(), reqwest::Error> {
+    let confirmation_link = format!("{}/user/email/confirm?token={}", base_url, token);
+    email_client
+        .send_email(
+            request_id,
+            new_email,
+            "Confirm your new email address",
+            &format!(
+                "Please confirm your new email address by clicking <a href=\"{}\">here</a>.",
+                confirmation_link
+            ),
+            &format!(
+                "Please confirm your new email address by visiting {}",
+                confirmation_link
+            ),
+        )
+        .await
+}
+
+async fn send_change_notification_email(
+    email_client: &EmailClient,
+    request_id: &RequestId,
+    changed: &ChangedEmail,
+) -> Result<(), reqwest::Error> {
+    let content = format!(
+        "The email address of your account was changed to {}. If you did not make this change, please contact support.",
+        changed.new_email
+    );
+    email_client
+        .send_email(
+            request_id,
+            &changed.old_email,
+            "Your email address was changed",
+            &content,
+            &content,
+        )
+        .await
+}
+
+#[tracing::instrument(name = "Check Email Taken In Database", skip(email, db_pool))]
+async fn check_if_email_taken(email: &str, db_pool: &ConnectionPool) -> Result<bool, sqlx::Error> {
+    let found_user = sqlx::query!(
+        r#"
+        SELECT id FROM users WHERE email = $1
+        "#,
+        email
+    )
+    .fetch_optional(&mut *db_pool.acquire().await?)
+    .await?;
+    Ok(found_user.is_some())
+}
+
+#[tracing::instrument(
+    name = "Store Email Change Request In Database",
+    skip(id, token, db_pool),
+    fields(id = %id)
+)]
+async fn store_email_change_request_repository(
+    id: Uuid,
+    new_email: &str,
+    token: &str,
+    db_pool: &ConnectionPool,
+) -> Result<(), sqlx::Error> {
+    let mut transaction = db_pool.begin().await?;
+    // Only the latest request of a user stays valid
+    sqlx::query!(
+        r#"
+        DELETE FROM email_change_requests WHERE user_id = $1
+        "#,
+        id
+    )
+    .execute(&mut *transaction)
+    .await?;
+    let result = sqlx::query!(
+        r#"
+        INSERT INTO email_change_requests (token, user_id, new_email, expires_at)
+        SELECT $1, id, $2, $3 FROM users WHERE id = $4
+        "#,
+        token,
+        new_email,
+        Utc::now() + Duration::hours(EMAIL_CHANGE_TOKEN_LIFETIME_HOURS),
+        id
+    )
+    .execute(&mut *transaction)
+    .await?;
+    if result.rows_affected() == 0 {
+        return Err(sqlx::Error::RowNotFound);
+    }
+    transaction.commit().await?;
+    Ok(())
+}
+
+struct ChangedEmail {
+    old_email: String,
+    new_email: String,
+}
+
+#[tracing::instrument(name = "Confirm Email Change In Database", skip(token, audit, db_pool))]
+async fn confirm_email_change_repository(
+    token: &str,
+    audit: &AuditContext,
+    db_pool: &ConnectionPool,
+) -> Result<ChangedEmail, sqlx::Error> {
+    let mut transaction = db_pool.begin().await?;
+    let request = sqlx::query!(
+        r#"
+        DELETE FROM email_change_requests
+        WHERE token = $1 AND expires_at > NOW()
+        RETURNING user_id, new_email
+        "#,
+        token
+    )
+    .fetch_optional(&mut *transaction)
+    .await?
+    .ok_or(sqlx::Error::RowNotFound)?;
+    let user = sqlx::query!(
+        r#"
+        SELECT email FROM users WHERE id = $1 FOR UPDATE
+        "#,
+        request.user_id
+    )
+    .fetch_one(&mut *transaction)
+    .await?;
+    // The unique constraint on users.email decides if the address is still free
+    sqlx::query!(
+        r#"
+        UPDATE users SET email = $1, updated_at = NOW() WHERE id = $2
+        "#,
+        request.new_email,
+        request.user_id
+    )
+    .execute(&mut *transaction)
+    .await?;
+    insert_audit_event(
+        &mut transaction,
+        audit,
+        AuditAction::UserUpdated,
+        request.user_id,
+        &["email"],
+    )
+    .await?;
+    transaction.commit().await?;
+    Ok(ChangedEmail {
+        old_email: user.email,
+        new_email: request.new_email,
+    })
+}
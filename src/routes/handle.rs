@@ -0,0 +1,150 @@
+use actix_web::{get, http::header, web, HttpResponse};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::user::{GetUser, User};
+use crate::metrics::ConnectionPool;
+use crate::validation::{validate_handle_charset, validate_handle_not_reserved};
+
+// How long an old handle keeps redirecting to its owner and stays unavailable to others
+pub const HANDLE_REDIRECT_GRACE_PERIOD_DAYS: i32 = 30;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum HandleUnavailableReason {
+    Invalid,
+    Reserved,
+    Taken,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HandleAvailability {
+    pub handle: String,
+    pub available: bool,
+    pub reason: Option<HandleUnavailableReason>,
+}
+
+// Get a user by handle via GET, old handles redirect to the current one
+#[get("/by-handle/{handle}")]
+#[tracing::instrument(name = "Get User By Handle", skip(db_pool))]
+pub async fn get_user_by_handle(
+    handle: web::Path<String>,
+    db_pool: web::Data<ConnectionPool>,
+) -> HttpResponse {
+    match get_user_by_handle_repository(&handle, &db_pool).await {
+        Ok(Some(user)) => return HttpResponse::Ok().json(user),
+        Ok(None) => {}
+        Err(_) => return HttpResponse::InternalServerError().body("Internal Server Error"),
+    }
+    match get_current_handle_for_old_handle_repository(&handle, &db_pool).await {
+        Ok(Some(current_handle)) => HttpResponse::TemporaryRedirect()
+            .insert_header((
+                header::LOCATION,
+                format!("/user/by-handle/{}", current_handle),
+            ))
+            .finish(),
+        Ok(None) => HttpResponse::NotFound().body("User not found"),
+        Err(_) => HttpResponse::InternalServerError().body("Internal Server Error"),
+    }
+}
+
+// Check whether a handle can be claimed via GET
+#[get("/by-handle/{handle}/availability")]
+#[tracing::instrument(name = "Check Handle Availability", skip(db_pool))]
+pub async fn get_handle_availability(
+    handle: web::Path<String>,
+    db_pool: web::Data<ConnectionPool>,
+) -> HttpResponse {
+    let handle = handle.into_inner();
+    match check_handle_availability(&handle, None, &db_pool).await {
+        Ok(reason) => HttpResponse::Ok().json(HandleAvailability {
+            handle,
+            available: reason.is_none(),
+            reason,
+        }),
+        Err(_) => HttpResponse::InternalServerError().body("Internal Server Error"),
+    }
+}
+
+// Returns why the handle cannot be used by `user_id`, None when it is available
+pub async fn check_handle_availability(
+    handle: &str,
+    user_id: Option<Uuid>,
+    db_pool: &ConnectionPool,
+) -> Result<Option<HandleUnavailableReason>, sqlx::Error> {
+    if validate_handle_charset(handle).is_err() {
+        return Ok(Some(HandleUnavailableReason::Invalid));
+    }
+    if validate_handle_not_reserved(handle).is_err() {
+        return Ok(Some(HandleUnavailableReason::Reserved));
+    }
+    if is_handle_held_by_other_user(handle, user_id, db_pool).await? {
+        return Ok(Some(HandleUnavailableReason::Taken));
+    }
+    Ok(None)
+}
+
+#[tracing::instrument(name = "Get User By Handle In Database", skip(db_pool))]
+async fn get_user_by_handle_repository(
+    handle: &str,
+    db_pool: &ConnectionPool,
+) -> Result<Option<GetUser>, sqlx::Error> {
+    let user = sqlx::query_as!(
+        User,
+        r#"
+        SELECT * FROM users WHERE lower(handle) = lower($1)
+        "#,
+        handle
+    )
+    .fetch_optional(&mut *db_pool.acquire().await?)
+    .await?;
+    Ok(user.map(|user| user.into()))
+}
+
+#[tracing::instrument(name = "Get Current Handle For Old Handle In Database", skip(db_pool))]
+async fn get_current_handle_for_old_handle_repository(
+    handle: &str,
+    db_pool: &ConnectionPool,
+) -> Result<Option<String>, sqlx::Error> {
+    let found = sqlx::query!(
+        r#"
+        SELECT users.handle FROM handle_history
+        JOIN users ON users.id = handle_history.user_id
+        WHERE lower(handle_history.handle) = lower($1)
+        AND handle_history.changed_at > NOW() - make_interval(days => $2)
+        ORDER BY handle_history.changed_at DESC
+        LIMIT 1
+        "#,
+        handle,
+        HANDLE_REDIRECT_GRACE_PERIOD_DAYS
+    )
+    .fetch_optional(&mut *db_pool.acquire().await?)
+    .await?;
+    Ok(found.and_then(|found| found.handle))
+}
+
+// Current handles and old handles still in their grace period belong to their user
+#[tracing::instrument(name = "Check Handle Held By Other User In Database", skip(db_pool))]
+async fn is_handle_held_by_other_user(
+    handle: &str,
+    user_id: Option<Uuid>,
+    db_pool: &ConnectionPool,
+) -> Result<bool, sqlx::Error> {
+    let found = sqlx::query!(
+        r#"
+        SELECT id AS "user_id!" FROM users
+        WHERE lower(handle) = lower($1) AND ($2::uuid IS NULL OR id <> $2)
+        UNION ALL
+        SELECT user_id FROM handle_history
+        WHERE lower(handle) = lower($1) AND ($2::uuid IS NULL OR user_id <> $2)
+        AND changed_at > NOW() - make_interval(days => $3)
+        LIMIT 1
+        "#,
+        handle,
+        user_id,
+        HANDLE_REDIRECT_GRACE_PERIOD_DAYS
+    )
+    .fetch_optional(&mut *db_pool.acquire().await?)
+    .await?;
+    Ok(found.is_some())
+}
@@ -0,0 +1,62 @@
+use std::time::Instant;
+
+use actix_web::{get, web, HttpResponse};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::metrics::ConnectionPool;
+use crate::migration::applied_migrations;
+use crate::ConfigurationEnvironment;
+
+// Captured by build.rs
+const GIT_COMMIT: &str = env!("BUILD_GIT_COMMIT");
+const BUILD_TIMESTAMP: &str = env!("BUILD_TIMESTAMP");
+const RUSTC_VERSION: &str = env!("BUILD_RUSTC_VERSION");
+const FEATURES: &str = env!("BUILD_FEATURES");
+
+// When the server was started, used to report uptime
+pub struct StartedAt(pub Instant);
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Info {
+    pub version: String,
+    pub git_commit: String,
+    pub build_timestamp: Option<DateTime<Utc>>,
+    pub rustc_version: String,
+    pub environment: String,
+    pub features: Vec<String>,
+    pub uptime_seconds: u64,
+    pub migrations: Vec<i64>,
+}
+
+// Describe the running build via GET
+#[get("/info")]
+#[tracing::instrument(name = "Get Info", skip_all)]
+pub async fn get_info(
+    environment: web::Data<ConfigurationEnvironment>,
+    started_at: web::Data<StartedAt>,
+    db_pool: web::Data<ConnectionPool>,
+) -> HttpResponse {
+    let migrations = match applied_migrations(db_pool.get_ref()).await {
+        Ok(migrations) => migrations,
+        Err(_) => return HttpResponse::InternalServerError().body("Internal Server Error"),
+    };
+    let build_timestamp = BUILD_TIMESTAMP
+        .parse()
+        .ok()
+        .and_then(|seconds| DateTime::<Utc>::from_timestamp(seconds, 0));
+    HttpResponse::Ok().json(Info {
+        version: env!("CARGO_PKG_VERSION").into(),
+        git_commit: GIT_COMMIT.into(),
+        build_timestamp,
+        rustc_version: RUSTC_VERSION.into(),
+        environment: environment.as_str().into(),
+        features: FEATURES
+            .split(',')
+            .filter(|feature| !feature.is_empty())
+            .map(str::to_owned)
+            .collect(),
+        uptime_seconds: started_at.0.elapsed().as_secs(),
+        migrations,
+    })
+}
@@ -0,0 +1,15 @@
+use actix_web::{get, web, HttpResponse};
+
+use crate::metrics::{ConnectionPool, METRICS};
+
+// Prometheus scrape endpoint, served on the admin port only
+#[get("/metrics")]
+pub async fn get_metrics(db_pool: web::Data<ConnectionPool>) -> HttpResponse {
+    METRICS.observe_pool(&db_pool);
+    match METRICS.render() {
+        Ok(body) => HttpResponse::Ok()
+            .content_type("text/plain; version=0.0.4")
+            .body(body),
+        Err(_) => HttpResponse::InternalServerError().body("Internal Server Error"),
+    }
+}
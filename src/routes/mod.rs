@@ -1,5 +1,21 @@
+pub mod admin;
+pub mod audit;
+pub mod avatar;
+pub mod email_change;
+pub mod handle;
 pub mod heath_check;
+pub mod info;
+pub mod metrics;
+pub mod readiness;
 pub mod user;
 
+pub use admin::*;
+pub use audit::*;
+pub use avatar::*;
+pub use email_change::*;
+pub use handle::*;
 pub use heath_check::*;
+pub use info::*;
+pub use metrics::*;
+pub use readiness::*;
 pub use user::*;
@@ -0,0 +1,134 @@
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+use actix_web::{get, web, HttpResponse};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::metrics::ConnectionPool;
+use crate::migration::pending_migrations;
+use crate::shutdown::Readiness;
+
+// Upper bound for a single dependency check
+const DEPENDENCY_CHECK_TIMEOUT: Duration = Duration::from_secs(1);
+// Reuse a report for this long so probes do not hammer the database
+const READINESS_CACHE_TTL: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ComponentStatus {
+    Up,
+    Down,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ComponentReport {
+    pub status: ComponentStatus,
+    // Whether a failure makes the whole application not ready
+    pub critical: bool,
+    pub details: Option<String>,
+    pub duration_ms: u128,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ReadinessReport {
+    pub ready: bool,
+    pub components: BTreeMap<String, ComponentReport>,
+}
+
+// Last readiness report, the lock also keeps concurrent probes from checking twice
+#[derive(Default)]
+pub struct ReadinessCache(Mutex<Option<(Instant, ReadinessReport)>>);
+
+// Readiness probe reporting the status of every dependency
+#[get("/ready")]
+#[tracing::instrument(name = "Readiness Check", skip_all)]
+async fn readiness_check(
+    readiness: web::Data<Readiness>,
+    cache: web::Data<ReadinessCache>,
+    db_pool: web::Data<ConnectionPool>,
+) -> HttpResponse {
+    // Shutting down, no need to look at dependencies
+    if !readiness.is_ready() {
+        let mut components = BTreeMap::new();
+        components.insert(
+            "shutdown".into(),
+            ComponentReport {
+                status: ComponentStatus::Down,
+                critical: true,
+                details: Some("Application is shutting down".into()),
+                duration_ms: 0,
+            },
+        );
+        return HttpResponse::ServiceUnavailable().json(ReadinessReport {
+            ready: false,
+            components,
+        });
+    }
+
+    let mut cached = cache.0.lock().await;
+    let report = match cached.as_ref() {
+        Some((checked_at, report)) if checked_at.elapsed() < READINESS_CACHE_TTL => report.clone(),
+        _ => {
+            let report = check_dependencies(&db_pool).await;
+            *cached = Some((Instant::now(), report.clone()));
+            report
+        }
+    };
+    if report.ready {
+        HttpResponse::Ok().json(report)
+    } else {
+        HttpResponse::ServiceUnavailable().json(report)
+    }
+}
+
+async fn check_dependencies(db_pool: &ConnectionPool) -> ReadinessReport {
+    let mut components = BTreeMap::new();
+    components.insert("database".into(), check_database(db_pool).await);
+    components.insert("migrations".into(), check_migrations(db_pool).await);
+    let ready = components
+        .values()
+        .all(|component| !component.critical || component.status == ComponentStatus::Up);
+    ReadinessReport { ready, components }
+}
+
+async fn check_database(db_pool: &ConnectionPool) -> ComponentReport {
+    let started_at = Instant::now();
+    let result = tokio::time::timeout(DEPENDENCY_CHECK_TIMEOUT, async {
+        sqlx::query("SELECT 1")
+            .execute(&mut *db_pool.acquire().await?)
+            .await
+    })
+    .await;
+    let (status, details) = match result {
+        Ok(Ok(_)) => (ComponentStatus::Up, None),
+        Ok(Err(e)) => (ComponentStatus::Down, Some(e.to_string())),
+        Err(_) => (ComponentStatus::Down, Some("Timed out".into())),
+    };
+    ComponentReport {
+        status,
+        critical: true,
+        details,
+        duration_ms: started_at.elapsed().as_millis(),
+    }
+}
+
+async fn check_migrations(db_pool: &ConnectionPool) -> ComponentReport {
+    let started_at = Instant::now();
+    let result = tokio::time::timeout(DEPENDENCY_CHECK_TIMEOUT, pending_migrations(db_pool)).await;
+    let (status, details) = match result {
+        Ok(Ok(pending)) if pending.is_empty() => (ComponentStatus::Up, None),
+        Ok(Ok(pending)) => (
+            ComponentStatus::Down,
+            Some(format!("Pending migrations: {:?}", pending)),
+        ),
+        Ok(Err(e)) => (ComponentStatus::Down, Some(e.to_string())),
+        Err(_) => (ComponentStatus::Down, Some("Timed out".into())),
+    };
+    ComponentReport {
+        status,
+        critical: true,
+        details,
+        duration_ms: started_at.elapsed().as_millis(),
+    }
+}
@@ -1,14 +1,22 @@
 use actix_web::{delete, get, post, put, web, HttpRequest, HttpResponse};
 use chrono::Utc;
+use secrecy::Secret;
 use serde::{Deserialize, Serialize};
-use sqlx::PgPool;
 use uuid::Uuid;
 use validator::Validate;
 
+use super::avatar::{get_avatar, upload_avatar};
+use super::email_change::{confirm_email_change, request_email_change};
+use super::handle::{check_handle_availability, get_handle_availability, get_user_by_handle};
+use crate::audit::{insert_audit_event, AuditAction, AuditContext};
+use crate::authentication::{hash_password, AuthenticatedUser};
+use crate::metrics::ConnectionPool;
+use crate::validation::{validate_handle, validate_locale, validate_timezone};
+
 // Get all users via GET
 #[get("/")]
 #[tracing::instrument(name = "Get All Users", skip(db_pool))]
-async fn get_users(db_pool: web::Data<PgPool>) -> HttpResponse {
+async fn get_users(db_pool: web::Data<ConnectionPool>) -> HttpResponse {
     let result = get_all_users_repository(&db_pool).await;
     match result {
         Ok(users) => HttpResponse::Ok().json(users),
@@ -17,14 +25,14 @@ async fn get_users(db_pool: web::Data<PgPool>) -> HttpResponse {
 }
 
 #[tracing::instrument(name = "Get All Users In Database", skip(db_pool))]
-async fn get_all_users_repository(db_pool: &PgPool) -> Result<Vec<GetUser>, sqlx::Error> {
+async fn get_all_users_repository(db_pool: &ConnectionPool) -> Result<Vec<GetUser>, sqlx::Error> {
     let users = sqlx::query_as!(
         User,
         r#"
         SELECT * FROM users
         "#
     )
-    .fetch_all(db_pool)
+    .fetch_all(&mut *db_pool.acquire().await?)
     .await?;
     Ok(users.into_iter().map(|user| user.into()).collect())
 }
@@ -32,7 +40,7 @@ async fn get_all_users_repository(db_pool: &PgPool) -> Result<Vec<GetUser>, sqlx
 #[tracing::instrument(name = "Get User", skip(db_pool),fields(id = %id))]
 // Get a user by id via GET
 #[get("/{id}")]
-async fn get_user(id: web::Path<Uuid>, db_pool: web::Data<PgPool>) -> HttpResponse {
+async fn get_user(id: web::Path<Uuid>, db_pool: web::Data<ConnectionPool>) -> HttpResponse {
     // Get user from database
     let result = get_user_by_id_repository(*id, &db_pool).await;
     match result {
@@ -46,6 +54,12 @@ pub struct GetUser {
     pub id: Uuid,
     pub name: String,
     pub email: String,
+    pub handle: Option<String>,
+    pub display_name: Option<String>,
+    pub locale: Option<String>,
+    pub timezone: Option<String>,
+    pub bio: Option<String>,
+    pub avatar_url: Option<String>,
     pub created_at: chrono::DateTime<Utc>,
     pub updated_at: chrono::DateTime<Utc>,
 }
@@ -56,6 +70,12 @@ impl From<User> for GetUser {
             id: user.id,
             name: user.name,
             email: user.email,
+            handle: user.handle,
+            display_name: user.display_name,
+            locale: user.locale,
+            timezone: user.timezone,
+            bio: user.bio,
+            avatar_url: user.avatar_url,
             created_at: user.created_at,
             updated_at: user.updated_at,
         }
@@ -64,16 +84,26 @@ impl From<User> for GetUser {
 
 // Create a new user via POST
 #[derive(Debug, Serialize, Deserialize)]
-struct User {
-    id: Uuid,
-    name: String,
-    email: String,
-    password: String,
-    created_at: chrono::DateTime<Utc>,
-    updated_at: chrono::DateTime<Utc>,
+pub(crate) struct User {
+    pub(crate) id: Uuid,
+    pub(crate) name: String,
+    pub(crate) email: String,
+    pub(crate) password: String,
+    pub(crate) display_name: Option<String>,
+    pub(crate) locale: Option<String>,
+    pub(crate) timezone: Option<String>,
+    pub(crate) bio: Option<String>,
+    pub(crate) avatar_url: Option<String>,
+    pub(crate) created_at: chrono::DateTime<Utc>,
+    pub(crate) updated_at: chrono::DateTime<Utc>,
+    pub(crate) handle: Option<String>,
+    pub(crate) is_admin: bool,
 }
 #[tracing::instrument(name = "Get User In Database", skip(id,db_pool),fields(id = %id))]
-async fn get_user_by_id_repository(id: Uuid, db_pool: &PgPool) -> Result<GetUser, sqlx::Error> {
+async fn get_user_by_id_repository(
+    id: Uuid,
+    db_pool: &ConnectionPool,
+) -> Result<GetUser, sqlx::Error> {
     let user = sqlx::query_as!(
         User,
         r#"
@@ -81,7 +111,7 @@ async fn get_user_by_id_repository(id: Uuid, db_pool: &PgPool) -> Result<GetUser
         "#,
         id
     )
-    .fetch_one(db_pool)
+    .fetch_one(&mut *db_pool.acquire().await?)
     .await?;
     Ok(user.into())
 }
@@ -99,14 +129,15 @@ struct CreateUser {
     #[validate(must_match = "password")]
     password_confirmation: String,
 }
-#[tracing::instrument(name = "Create User", skip(json,db_pool),fields(name = %json.name, email = %json.email))]
+#[tracing::instrument(name = "Create User", skip(json,audit,db_pool),fields(name = %json.name, email = %json.email))]
 #[post("/")]
 async fn create_user(
     _req: HttpRequest,
     json: web::Json<CreateUser>,
-    db_pool: web::Data<PgPool>,
+    audit: AuditContext,
+    db_pool: web::Data<ConnectionPool>,
 ) -> HttpResponse {
-    let result = create_user_repository(json.into_inner(), &db_pool).await;
+    let result = create_user_repository(json.into_inner(), &audit, &db_pool).await;
     match result {
         Ok(id) => HttpResponse::Ok().json(id),
         // Found user
@@ -115,8 +146,12 @@ async fn create_user(
     }
 }
 
-#[tracing::instrument(name = "Create User In Database", skip(user,db_pool),fields(name = %user.name, email = %user.email))]
-async fn create_user_repository(user: CreateUser, db_pool: &PgPool) -> Result<Uuid, sqlx::Error> {
+#[tracing::instrument(name = "Create User In Database", skip(user,audit,db_pool),fields(name = %user.name, email = %user.email))]
+async fn create_user_repository(
+    user: CreateUser,
+    audit: &AuditContext,
+    db_pool: &ConnectionPool,
+) -> Result<Uuid, sqlx::Error> {
     // Check if user already exists
     let is_exist = check_if_user_exists(&user.email, db_pool).await;
     let is_exist = match is_exist {
@@ -128,6 +163,8 @@ async fn create_user_repository(user: CreateUser, db_pool: &PgPool) -> Result<Uu
     }
 
     let id = Uuid::new_v4();
+    let password_hash = hash_password(Secret::new(user.password)).await;
+    let mut transaction = db_pool.begin().await?;
     sqlx::query!(
         r#"
         INSERT INTO users (id, name, email, password)
@@ -136,10 +173,19 @@ async fn create_user_repository(user: CreateUser, db_pool: &PgPool) -> Result<Uu
         &id,
         user.name,
         user.email,
-        user.password
+        password_hash
+    )
+    .execute(&mut *transaction)
+    .await?;
+    insert_audit_event(
+        &mut transaction,
+        audit,
+        AuditAction::UserCreated,
+        id,
+        &["name", "email", "password"],
     )
-    .execute(db_pool)
     .await?;
+    transaction.commit().await?;
     Ok(id)
 }
 
@@ -150,96 +196,221 @@ pub struct UpdateUser {
     name: Option<String>,
     #[validate(length(min = 8, max = 255))]
     password: Option<String>,
+    #[validate(length(min = 1, max = 255))]
+    display_name: Option<String>,
+    // BCP-47 language tag, e.g. en-GB
+    #[validate(custom = "validate_locale")]
+    locale: Option<String>,
+    // IANA timezone name, e.g. Europe/London
+    #[validate(custom = "validate_timezone")]
+    timezone: Option<String>,
+    #[validate(length(max = 1000))]
+    bio: Option<String>,
+    #[validate(url)]
+    avatar_url: Option<String>,
+    // Unique @handle, compared case-insensitively
+    #[validate(custom = "validate_handle")]
+    handle: Option<String>,
 }
-#[tracing::instrument(name = "Update User", skip(json, db_pool) ,fields(id = %id))]
+#[tracing::instrument(name = "Update User", skip(json, user, audit, db_pool) ,fields(id = %id))]
 #[put("/{id}")]
 async fn update_user(
     _req: HttpRequest,
     id: web::Path<String>,
     json: web::Json<UpdateUser>,
-    db_pool: web::Data<PgPool>,
+    user: AuthenticatedUser,
+    audit: AuditContext,
+    db_pool: web::Data<ConnectionPool>,
 ) -> HttpResponse {
     // Parse id to uuid
     let id = match Uuid::parse_str(&id) {
         Ok(id) => id,
         Err(_) => return HttpResponse::BadRequest().body("Invalid id"),
     };
+    if !user.can_manage(id) {
+        return HttpResponse::Forbidden().body("Forbidden");
+    }
+    // Validate user input
+    if let Err(errors) = json.validate() {
+        return HttpResponse::BadRequest().json(errors);
+    }
+    // Check the new handle is not held by someone else
+    if let Some(handle) = &json.handle {
+        match check_handle_availability(handle, Some(id), &db_pool).await {
+            Ok(None) => {}
+            Ok(Some(_)) => return HttpResponse::Conflict().body("Handle already taken"),
+            Err(_) => return HttpResponse::InternalServerError().body("Internal Server Error"),
+        }
+    }
     // Update user in database
-    let result = update_user_repository(id, json.into_inner(), &db_pool).await;
+    let result = update_user_repository(id, json.into_inner(), &audit, &db_pool).await;
     match result {
         Ok(_) => HttpResponse::Ok().body("User updated"),
         // Could not found user
         Err(sqlx::Error::RowNotFound) => HttpResponse::NotFound().body("User not found"),
+        // Handle claimed concurrently
+        Err(sqlx::Error::Database(e)) if e.code().as_deref() == Some("23505") => {
+            HttpResponse::Conflict().body("Handle already taken")
+        }
         Err(_) => HttpResponse::InternalServerError().body("Internal Server Error"),
     }
 }
-#[tracing::instrument(name = "Update User In Database", skip(id, user, db_pool),fields(id = %id))]
+// Names of the fields whose value differs from the stored user
+fn changed_fields(user: &UpdateUser, found_user: &User) -> Vec<&'static str> {
+    fn differs(new: &Option<String>, old: Option<&String>) -> bool {
+        new.as_ref().is_some_and(|new| Some(new) != old)
+    }
+    [
+        ("name", differs(&user.name, Some(&found_user.name))),
+        // Only the hash is stored, so any new password counts as a change
+        ("password", user.password.is_some()),
+        (
+            "display_name",
+            differs(&user.display_name, found_user.display_name.as_ref()),
+        ),
+        ("locale", differs(&user.locale, found_user.locale.as_ref())),
+        (
+            "timezone",
+            differs(&user.timezone, found_user.timezone.as_ref()),
+        ),
+        ("bio", differs(&user.bio, found_user.bio.as_ref())),
+        (
+            "avatar_url",
+            differs(&user.avatar_url, found_user.avatar_url.as_ref()),
+        ),
+        ("handle", differs(&user.handle, found_user.handle.as_ref())),
+    ]
+    .into_iter()
+    .filter(|(_, changed)| *changed)
+    .map(|(field, _)| field)
+    .collect()
+}
+
+#[tracing::instrument(name = "Update User In Database", skip(id, user, audit, db_pool),fields(id = %id))]
 async fn update_user_repository(
     id: Uuid,
     user: UpdateUser,
-    db_pool: &PgPool,
+    audit: &AuditContext,
+    db_pool: &ConnectionPool,
 ) -> Result<(), sqlx::Error> {
+    let mut transaction = db_pool.begin().await?;
     // Get user from database
     let found_user = sqlx::query_as!(
         User,
         r#"
-        SELECT * FROM users WHERE id = $1
+        SELECT * FROM users WHERE id = $1 FOR UPDATE
         "#,
         id
     )
-    .fetch_one(db_pool)
+    .fetch_one(&mut *transaction)
     .await;
     let found_user = match found_user {
         Ok(user) => user,
         Err(_) => return Err(sqlx::Error::RowNotFound),
     };
+    let changed_fields = changed_fields(&user, &found_user);
+    let password_hash = match user.password {
+        Some(password) => hash_password(Secret::new(password)).await,
+        None => found_user.password,
+    };
+    // Keep the previous handle so it redirects during the grace period
+    if let (Some(old_handle), Some(new_handle)) = (&found_user.handle, &user.handle) {
+        if old_handle.to_lowercase() != new_handle.to_lowercase() {
+            sqlx::query!(
+                r#"
+                INSERT INTO handle_history (handle, user_id)
+                VALUES ($1, $2)
+                "#,
+                old_handle,
+                id
+            )
+            .execute(&mut *transaction)
+            .await?;
+        }
+    }
     // Update user
     sqlx::query!(
         r#"
-        UPDATE users SET name = $1, password = $2 WHERE id = $3
+        UPDATE users
+        SET name = $1, password = $2, display_name = $3, locale = $4, timezone = $5, bio = $6, avatar_url = $7, handle = $8
+        WHERE id = $9
         "#,
         user.name.to_owned().unwrap_or(found_user.name),
-        user.password.to_owned().unwrap_or(found_user.password),
+        password_hash,
+        user.display_name.or(found_user.display_name),
+        user.locale.or(found_user.locale),
+        user.timezone.or(found_user.timezone),
+        user.bio.or(found_user.bio),
+        user.avatar_url.or(found_user.avatar_url),
+        user.handle.or(found_user.handle),
         id
     )
-    .execute(db_pool)
+    .execute(&mut *transaction)
+    .await?;
+    insert_audit_event(
+        &mut transaction,
+        audit,
+        AuditAction::UserUpdated,
+        id,
+        &changed_fields,
+    )
     .await?;
+    transaction.commit().await?;
     Ok(())
 }
 
 // Delete a user via DELETE
 #[delete("/{id}")]
-#[tracing::instrument(name = "Delete User", skip(id,db_pool),fields(id = %id))]
-async fn delete_user(id: web::Path<Uuid>, db_pool: web::Data<PgPool>) -> HttpResponse {
+#[tracing::instrument(name = "Delete User", skip(id,user,audit,db_pool),fields(id = %id))]
+async fn delete_user(
+    id: web::Path<Uuid>,
+    user: AuthenticatedUser,
+    audit: AuditContext,
+    db_pool: web::Data<ConnectionPool>,
+) -> HttpResponse {
+    if !user.can_manage(*id) {
+        return HttpResponse::Forbidden().body("Forbidden");
+    }
     // Delete user from database
-    let result = delete_user_repository(*id, &db_pool).await;
+    let result = delete_user_repository(*id, &audit, &db_pool).await;
     match result {
         Ok(_) => HttpResponse::Ok().body("User deleted"),
         Err(_) => HttpResponse::InternalServerError().body("Internal Server Error"),
     }
 }
 
-#[tracing::instrument(name = "Delete User In Database", skip(id,db_pool),fields(id = %id))]
-async fn delete_user_repository(id: Uuid, db_pool: &PgPool) -> Result<(), sqlx::Error> {
-    sqlx::query!(
+#[tracing::instrument(name = "Delete User In Database", skip(id,audit,db_pool),fields(id = %id))]
+async fn delete_user_repository(
+    id: Uuid,
+    audit: &AuditContext,
+    db_pool: &ConnectionPool,
+) -> Result<(), sqlx::Error> {
+    let mut transaction = db_pool.begin().await?;
+    let result = sqlx::query!(
         r#"
         DELETE FROM users WHERE id = $1
         "#,
         id
     )
-    .execute(db_pool)
+    .execute(&mut *transaction)
     .await?;
+    // Nothing to record when the user did not exist
+    if result.rows_affected() > 0 {
+        insert_audit_event(&mut transaction, audit, AuditAction::UserDeleted, id, &[]).await?;
+    }
+    transaction.commit().await?;
     Ok(())
 }
 
-async fn check_if_user_exists(email: &str, db_pool: &PgPool) -> Result<bool, sqlx::Error> {
+#[tracing::instrument(name = "Check User Exists In Database", skip(email, db_pool))]
+async fn check_if_user_exists(email: &str, db_pool: &ConnectionPool) -> Result<bool, sqlx::Error> {
     let found_user = sqlx::query!(
         r#"
         SELECT * FROM users WHERE email = $1
         "#,
         email
     )
-    .fetch_optional(db_pool)
+    .fetch_optional(&mut *db_pool.acquire().await?)
     .await?;
     if found_user.is_some() {
         return Ok(true);
@@ -254,6 +425,12 @@ pub fn init_user_routes(cfg: &mut web::ServiceConfig) {
             .service(get_user)
             .service(create_user)
             .service(update_user)
-            .service(delete_user),
+            .service(delete_user)
+            .service(upload_avatar)
+            .service(get_avatar)
+            .service(get_user_by_handle)
+            .service(get_handle_availability)
+            .service(request_email_change)
+            .service(confirm_email_change),
     );
 }
@@ -0,0 +1,114 @@
+use std::future::Future;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+};
+use std::time::Duration;
+
+use actix_web::dev;
+use futures_util::future::BoxFuture;
+
+// Whether the application should receive traffic, flipped off when shutdown starts
+#[derive(Clone)]
+pub struct Readiness(Arc<AtomicBool>);
+
+impl Readiness {
+    pub fn new() -> Self {
+        Self(Arc::new(AtomicBool::new(true)))
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    pub fn set_not_ready(&self) {
+        self.0.store(false, Ordering::SeqCst);
+    }
+}
+
+impl Default for Readiness {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+type ShutdownHook = Box<dyn FnOnce() -> BoxFuture<'static, ()> + Send>;
+
+// Controls the shutdown of a running server
+#[derive(Clone)]
+pub struct ServerHandle {
+    server: dev::ServerHandle,
+    readiness: Readiness,
+    // Time between reporting not ready and draining
+    readiness_delay: Duration,
+    hooks: Arc<Mutex<Vec<(String, ShutdownHook)>>>,
+}
+
+impl ServerHandle {
+    pub fn new(server: dev::ServerHandle, readiness: Readiness, readiness_delay: Duration) -> Self {
+        Self {
+            server,
+            readiness,
+            readiness_delay,
+            hooks: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    // Hooks run once, in registration order, after in-flight requests are drained
+    pub fn register_shutdown_hook<F, Fut>(&self, name: &str, hook: F)
+    where
+        F: FnOnce() -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.hooks
+            .lock()
+            .expect("Shutdown hooks lock poisoned.")
+            .push((name.into(), Box::new(move || Box::pin(hook()))));
+    }
+
+    pub fn readiness(&self) -> Readiness {
+        self.readiness.clone()
+    }
+
+    // Report not ready, wait for load balancers to notice, drain in-flight requests
+    // within the shutdown timeout, then run hooks
+    #[tracing::instrument(name = "Shutdown", skip(self))]
+    pub async fn shutdown(&self) {
+        tracing::info!("Marking application as not ready");
+        self.readiness.set_not_ready();
+        if !self.readiness_delay.is_zero() {
+            tracing::info!(delay = ?self.readiness_delay, "Waiting before draining");
+            actix_web::rt::time::sleep(self.readiness_delay).await;
+        }
+        tracing::info!("Draining in-flight requests");
+        self.server.stop(true).await;
+        let hooks = std::mem::take(&mut *self.hooks.lock().expect("Shutdown hooks lock poisoned."));
+        for (name, hook) in hooks {
+            tracing::info!(hook = %name, "Running shutdown hook");
+            hook().await;
+        }
+        tracing::info!("Shutdown complete");
+    }
+}
+
+// Resolves on SIGTERM or Ctrl-C
+pub async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("Failed to listen for Ctrl-C.");
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("Failed to listen for SIGTERM.")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+}
@@ -1,27 +1,248 @@
-use std::{io::Error, net::TcpListener};
+use std::{
+    io::Error,
+    net::TcpListener,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
-use crate::routes::{health_check, user};
+use crate::email_client::EmailClient;
+use crate::metrics::{ConnectionPool, RequestMetrics};
+use crate::migration::run_migrations;
+use crate::reload::SettingsHandle;
+use crate::request_id::{RequestIdRootSpanBuilder, RequestIdentity};
+use crate::routes::{
+    admin, audit, get_info, get_metrics, health_check, readiness_check, user, ReadinessCache,
+    StartedAt,
+};
+use crate::shutdown::{shutdown_signal, Readiness, ServerHandle};
+use crate::storage::{build_blob_store, BlobStore};
+use crate::telemetry::LogFilter;
+use crate::{DatabaseSettings, Settings};
 use actix_web::{dev::Server, web, App, HttpServer};
-use sqlx::PgPool;
+use futures_util::future::{select, Either};
 use tracing_actix_web::TracingLogger;
 
+// Owns everything needed to serve requests, shared by the binary and the tests
+pub struct Application {
+    port: u16,
+    admin_port: u16,
+    server: Server,
+    admin_server: Server,
+    server_handle: ServerHandle,
+    settings: SettingsHandle,
+}
+
+impl Application {
+    pub async fn build(configuration: Settings, log_filter: LogFilter) -> Result<Self, Error> {
+        // Setup PostgreSQL connection pool
+        let connection_pool = get_connection_pool(&configuration.database);
+        if configuration.database.pool.connect_eagerly {
+            verify_connection_pool(&connection_pool, &configuration.database).await?;
+        }
+        if configuration.database.run_migrations_on_startup {
+            run_migrations(&configuration.database)
+                .await
+                .map_err(Error::other)?;
+        }
+
+        // Setup blob storage for uploads
+        let blob_store = build_blob_store(&configuration.storage).map_err(Error::other)?;
+
+        // Setup email client
+        let email_client = configuration.email_client.client();
+
+        // Create Tcp listener, port 0 picks a random free port
+        let address = format!(
+            "{}:{}",
+            configuration.application.host_address, configuration.application.port
+        );
+        let tcp_listener = TcpListener::bind(address)?;
+        let port = tcp_listener.local_addr()?.port();
+        let admin_address = format!(
+            "{}:{}",
+            configuration.application.host_address, configuration.application.admin_port
+        );
+        let admin_listener = TcpListener::bind(admin_address)?;
+        let admin_port = admin_listener.local_addr()?.port();
+        let admin_server = run_admin(admin_listener, connection_pool.clone(), log_filter)?;
+        let readiness = Readiness::new();
+        let readiness_delay = Duration::from_millis(
+            configuration
+                .application
+                .shutdown_readiness_delay_milliseconds,
+        );
+        let settings = SettingsHandle::new(configuration);
+        let server = run(
+            tcp_listener,
+            connection_pool.clone(),
+            blob_store,
+            email_client,
+            readiness.clone(),
+            settings.clone(),
+        )?;
+        let server_handle = ServerHandle::new(server.handle(), readiness, readiness_delay);
+        let admin_server_handle = admin_server.handle();
+        server_handle.register_shutdown_hook("stop admin server", move || async move {
+            admin_server_handle.stop(true).await;
+        });
+        server_handle.register_shutdown_hook("close database pool", move || async move {
+            connection_pool.close().await;
+        });
+        Ok(Self {
+            port,
+            admin_port,
+            server,
+            admin_server,
+            server_handle,
+            settings,
+        })
+    }
+
+    // Port actually bound by the listener
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    // Port actually bound by the admin listener
+    pub fn admin_port(&self) -> u16 {
+        self.admin_port
+    }
+
+    // Settings handlers read, updated by configuration reloads
+    pub fn settings_handle(&self) -> SettingsHandle {
+        self.settings.clone()
+    }
+
+    // Handle to register shutdown hooks or trigger shutdown
+    pub fn server_handle(&self) -> ServerHandle {
+        self.server_handle.clone()
+    }
+
+    // Run until stopped through the server handle or by SIGTERM / Ctrl-C
+    pub async fn run_until_stopped(self) -> Result<(), Error> {
+        // Stopped by a shutdown hook
+        actix_web::rt::spawn(self.admin_server);
+        let server_handle = self.server_handle;
+        let on_signal = Box::pin(async move {
+            shutdown_signal().await;
+            server_handle.shutdown().await;
+        });
+        match select(self.server, on_signal).await {
+            Either::Left((result, _)) => result,
+            // Shutdown hooks have completed, collect the server result
+            Either::Right(((), server)) => server.await,
+        }
+    }
+}
+
+// Create a lazy PostgreSQL connection pool
+pub fn get_connection_pool(configuration: &DatabaseSettings) -> ConnectionPool {
+    configuration
+        .pool
+        .options()
+        .connect_lazy_with(configuration.with_database())
+        .into()
+}
+
+// Fail at startup instead of on the first request when the database is unreachable
+#[tracing::instrument(name = "Verify Connection Pool", skip_all)]
+pub async fn verify_connection_pool(
+    connection_pool: &ConnectionPool,
+    configuration: &DatabaseSettings,
+) -> Result<(), Error> {
+    let result = async {
+        sqlx::query("SELECT 1")
+            .execute(&mut *connection_pool.acquire().await?)
+            .await
+    };
+    result.await.map_err(|e| {
+        Error::other(format!(
+            "Failed to connect to database {} at {}:{}: {}",
+            configuration.database_name, configuration.host, configuration.port, e
+        ))
+    })?;
+    Ok(())
+}
+
 // Create HttpServer using actix-web
-pub fn run(tcp_listener: TcpListener, connection_pool: PgPool) -> Result<Server, Error> {
+pub fn run(
+    tcp_listener: TcpListener,
+    connection_pool: ConnectionPool,
+    blob_store: Arc<dyn BlobStore>,
+    email_client: EmailClient,
+    readiness: Readiness,
+    settings: SettingsHandle,
+) -> Result<Server, Error> {
     // Register connection pool as data
     let database_connection_pool = web::Data::new(connection_pool);
+    // Register blob store as data
+    let blob_store: web::Data<dyn BlobStore> = web::Data::from(blob_store);
+    // Register email client as data
+    let email_client = web::Data::new(email_client);
+    // Register readiness as data
+    let readiness = web::Data::new(readiness);
+    let readiness_cache = web::Data::new(ReadinessCache::default());
+    // Register build and runtime info as data
+    let configuration = settings.load();
+    let environment = web::Data::new(configuration.environment.clone());
+    let started_at = web::Data::new(StartedAt(Instant::now()));
+    // Register reloadable settings as data
+    let settings = web::Data::new(settings);
     // Create HttpServer instance
     let server = HttpServer::new(move || {
         // Create App instance
         App::new()
-            .wrap(TracingLogger::default())
+            .wrap(RequestMetrics)
+            .wrap(TracingLogger::<RequestIdRootSpanBuilder>::new())
+            .wrap(RequestIdentity)
             .app_data(database_connection_pool.clone())
+            .app_data(blob_store.clone())
+            .app_data(email_client.clone())
+            .app_data(readiness.clone())
+            .app_data(readiness_cache.clone())
+            .app_data(environment.clone())
+            .app_data(started_at.clone())
+            .app_data(settings.clone())
             // Register handler for GET /health_check
             .service(health_check)
+            // Register handler for GET /ready
+            .service(readiness_check)
+            // Register handler for GET /info
+            .service(get_info)
             .configure(user::init_user_routes)
+            .configure(audit::init_audit_routes)
     })
     .listen(tcp_listener)?
+    // Signals are handled by Application to run shutdown hooks
+    .disable_signals()
+    .shutdown_timeout(configuration.application.shutdown_timeout_seconds)
     .run();
 
     // Return HttpServer instance
     Ok(server)
 }
+
+// Create the admin HttpServer, only reachable on the admin port
+pub fn run_admin(
+    tcp_listener: TcpListener,
+    connection_pool: ConnectionPool,
+    log_filter: LogFilter,
+) -> Result<Server, Error> {
+    let database_connection_pool = web::Data::new(connection_pool);
+    let log_filter = web::Data::new(log_filter);
+    let server = HttpServer::new(move || {
+        App::new()
+            .wrap(TracingLogger::default())
+            .app_data(database_connection_pool.clone())
+            .app_data(log_filter.clone())
+            // Register handler for GET /metrics
+            .service(get_metrics)
+            // Register handler for PUT /admin/log-level
+            .configure(admin::init_admin_routes)
+    })
+    .listen(tcp_listener)?
+    .workers(1)
+    .disable_signals()
+    .run();
+    Ok(server)
+}
@@ -0,0 +1,59 @@
+use std::path::PathBuf;
+
+use actix_web::web::Bytes;
+use async_trait::async_trait;
+
+use super::{validate_key, BlobStore, BlobStoreError};
+
+// Blob store keeping every blob as a file below a root directory
+pub struct LocalBlobStore {
+    root: PathBuf,
+}
+
+impl LocalBlobStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    fn path(&self, key: &str) -> Result<PathBuf, BlobStoreError> {
+        validate_key(key)?;
+        Ok(self.root.join(key))
+    }
+}
+
+#[async_trait]
+impl BlobStore for LocalBlobStore {
+    // Files carry no content type, readers sniff it from the stored bytes
+    #[tracing::instrument(name = "Put Blob In Local Storage", skip(self, data, _content_type))]
+    async fn put(&self, key: &str, data: Bytes, _content_type: &str) -> Result<(), BlobStoreError> {
+        let path = self.path(key)?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        // Write to a temporary file first so readers never see partial blobs
+        let temporary_path = path.with_extension("partial");
+        tokio::fs::write(&temporary_path, &data).await?;
+        tokio::fs::rename(&temporary_path, &path).await?;
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Get Blob From Local Storage", skip(self))]
+    async fn get(&self, key: &str) -> Result<Option<Bytes>, BlobStoreError> {
+        let path = self.path(key)?;
+        match tokio::fs::read(&path).await {
+            Ok(data) => Ok(Some(Bytes::from(data))),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    #[tracing::instrument(name = "Delete Blob From Local Storage", skip(self))]
+    async fn delete(&self, key: &str) -> Result<(), BlobStoreError> {
+        let path = self.path(key)?;
+        match tokio::fs::remove_file(&path).await {
+            Ok(_) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
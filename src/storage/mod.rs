@@ -0,0 +1,80 @@
+use std::{fmt, sync::Arc};
+
+use actix_web::web::Bytes;
+use async_trait::async_trait;
+
+use crate::{StorageBackend, StorageSettings};
+
+mod local;
+mod s3;
+
+pub use self::s3::S3BlobStore;
+pub use local::LocalBlobStore;
+
+#[derive(Debug)]
+pub enum BlobStoreError {
+    // Key is empty or tries to escape the store
+    InvalidKey(String),
+    Io(std::io::Error),
+    // Error reported by a remote backend
+    Backend(String),
+}
+
+impl fmt::Display for BlobStoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BlobStoreError::InvalidKey(key) => write!(f, "'{}' is not a valid blob key", key),
+            BlobStoreError::Io(e) => write!(f, "Blob store IO error: {}", e),
+            BlobStoreError::Backend(e) => write!(f, "Blob store backend error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for BlobStoreError {}
+
+impl From<std::io::Error> for BlobStoreError {
+    fn from(e: std::io::Error) -> Self {
+        BlobStoreError::Io(e)
+    }
+}
+
+// Storage for binary objects such as avatars, addressed by `/` separated keys
+#[async_trait]
+pub trait BlobStore: Send + Sync {
+    async fn put(&self, key: &str, data: Bytes, content_type: &str) -> Result<(), BlobStoreError>;
+    // Returns None when no blob is stored under the key
+    async fn get(&self, key: &str) -> Result<Option<Bytes>, BlobStoreError>;
+    async fn delete(&self, key: &str) -> Result<(), BlobStoreError>;
+}
+
+// Create the blob store selected in the settings
+pub fn build_blob_store(settings: &StorageSettings) -> Result<Arc<dyn BlobStore>, BlobStoreError> {
+    match settings.backend {
+        StorageBackend::Local => Ok(Arc::new(LocalBlobStore::new(&settings.local_path))),
+        StorageBackend::S3 => {
+            let s3_settings = settings.s3.as_ref().ok_or_else(|| {
+                BlobStoreError::Backend(
+                    "storage.s3 settings are required for the s3 backend".into(),
+                )
+            })?;
+            Ok(Arc::new(S3BlobStore::new(s3_settings)?))
+        }
+    }
+}
+
+// Keys are relative paths made of plain segments
+fn validate_key(key: &str) -> Result<(), BlobStoreError> {
+    let is_valid = !key.is_empty()
+        && key.split('/').all(|segment| {
+            !segment.is_empty()
+                && segment != "."
+                && segment != ".."
+                && segment
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        });
+    if is_valid {
+        return Ok(());
+    }
+    Err(BlobStoreError::InvalidKey(key.into()))
+}
@@ -0,0 +1,93 @@
+use actix_web::web::Bytes;
+use async_trait::async_trait;
+use s3::{creds::Credentials, Bucket, Region};
+use secrecy::ExposeSecret;
+
+use super::{validate_key, BlobStore, BlobStoreError};
+use crate::S3Settings;
+
+// Blob store backed by any S3 compatible object storage (AWS S3, MinIO, ...)
+pub struct S3BlobStore {
+    bucket: Bucket,
+}
+
+impl S3BlobStore {
+    pub fn new(settings: &S3Settings) -> Result<Self, BlobStoreError> {
+        let region = Region::Custom {
+            region: settings.region.clone(),
+            endpoint: settings.endpoint.clone(),
+        };
+        let credentials = Credentials::new(
+            Some(&settings.access_key),
+            Some(settings.secret_key.expose_secret()),
+            None,
+            None,
+            None,
+        )
+        .map_err(|e| BlobStoreError::Backend(e.to_string()))?;
+        let bucket = Bucket::new(&settings.bucket, region, credentials)
+            .map_err(|e| BlobStoreError::Backend(e.to_string()))?;
+        // Self hosted stores usually do not support virtual hosted buckets
+        let bucket = if settings.path_style {
+            bucket.with_path_style()
+        } else {
+            bucket
+        };
+        Ok(Self { bucket })
+    }
+}
+
+#[async_trait]
+impl BlobStore for S3BlobStore {
+    #[tracing::instrument(name = "Put Blob In S3", skip(self, data))]
+    async fn put(&self, key: &str, data: Bytes, content_type: &str) -> Result<(), BlobStoreError> {
+        validate_key(key)?;
+        let response = self
+            .bucket
+            .put_object_with_content_type(key, &data, content_type)
+            .await
+            .map_err(|e| BlobStoreError::Backend(e.to_string()))?;
+        match response.status_code() {
+            200..=299 => Ok(()),
+            status => Err(BlobStoreError::Backend(format!(
+                "Unexpected status {} when storing '{}'",
+                status, key
+            ))),
+        }
+    }
+
+    #[tracing::instrument(name = "Get Blob From S3", skip(self))]
+    async fn get(&self, key: &str) -> Result<Option<Bytes>, BlobStoreError> {
+        validate_key(key)?;
+        let response = self
+            .bucket
+            .get_object(key)
+            .await
+            .map_err(|e| BlobStoreError::Backend(e.to_string()))?;
+        match response.status_code() {
+            200..=299 => Ok(Some(response.bytes().clone())),
+            404 => Ok(None),
+            status => Err(BlobStoreError::Backend(format!(
+                "Unexpected status {} when fetching '{}'",
+                status, key
+            ))),
+        }
+    }
+
+    #[tracing::instrument(name = "Delete Blob From S3", skip(self))]
+    async fn delete(&self, key: &str) -> Result<(), BlobStoreError> {
+        validate_key(key)?;
+        let response = self
+            .bucket
+            .delete_object(key)
+            .await
+            .map_err(|e| BlobStoreError::Backend(e.to_string()))?;
+        match response.status_code() {
+            200..=299 | 404 => Ok(()),
+            status => Err(BlobStoreError::Backend(format!(
+                "Unexpected status {} when deleting '{}'",
+                status, key
+            ))),
+        }
+    }
+}
@@ -0,0 +1,60 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use tracing_subscriber::filter::ParseError;
+use tracing_subscriber::{reload, EnvFilter, Registry};
+
+// Handle to swap the EnvFilter of a running subscriber
+#[derive(Clone)]
+pub struct LogFilter {
+    handle: reload::Handle<EnvFilter, Registry>,
+    // Bumped on every change so a pending revert does not undo a newer change
+    generation: Arc<AtomicU64>,
+}
+
+#[derive(Debug)]
+pub enum LogFilterError {
+    InvalidDirectives(ParseError),
+    Reload(reload::Error),
+}
+
+impl std::fmt::Display for LogFilterError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LogFilterError::InvalidDirectives(e) => write!(f, "Invalid filter directives: {}", e),
+            LogFilterError::Reload(e) => write!(f, "Failed to reload filter: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for LogFilterError {}
+
+impl LogFilter {
+    pub fn new(handle: reload::Handle<EnvFilter, Registry>) -> Self {
+        Self {
+            handle,
+            generation: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    // Directives currently in effect
+    pub fn current(&self) -> Option<String> {
+        self.handle.with_current(|filter| filter.to_string()).ok()
+    }
+
+    // Replace the filter, returns the generation of the change
+    pub fn set(&self, directives: &str) -> Result<u64, LogFilterError> {
+        let filter = EnvFilter::try_new(directives).map_err(LogFilterError::InvalidDirectives)?;
+        self.handle.reload(filter).map_err(LogFilterError::Reload)?;
+        Ok(self.generation.fetch_add(1, Ordering::SeqCst) + 1)
+    }
+
+    // Restore directives unless the filter was changed again since `generation`
+    pub fn revert(&self, directives: &str, generation: u64) -> Result<bool, LogFilterError> {
+        if self.generation.load(Ordering::SeqCst) != generation {
+            return Ok(false);
+        }
+        self.set(directives)?;
+        Ok(true)
+    }
+}
@@ -1,26 +1,75 @@
+use opentelemetry::sdk::trace::Tracer;
 use tracing::Subscriber;
 use tracing_bunyan_formatter::{BunyanFormattingLayer, JsonStorageLayer};
 use tracing_log::LogTracer;
+use tracing_subscriber::filter::filter_fn;
 use tracing_subscriber::fmt::MakeWriter;
-use tracing_subscriber::{prelude::__tracing_subscriber_SubscriberExt, EnvFilter, Registry};
+use tracing_subscriber::layer::Layer;
+use tracing_subscriber::{
+    prelude::__tracing_subscriber_SubscriberExt, reload, EnvFilter, Registry,
+};
 
-// Create subscriber
+use crate::metrics::{QueryMetricsLayer, REPOSITORY_SPAN_SUFFIX};
+use crate::LogFormat;
+
+mod log_filter;
+mod otlp;
+mod redaction;
+mod writer;
+
+pub use log_filter::*;
+pub use otlp::*;
+pub use redaction::*;
+pub use writer::*;
+
+type FormatLayer = Box<dyn Layer<Registry> + Send + Sync>;
+
+// Create subscriber, fields are rendered through the redaction rules and
+// spans are also exported when a tracer is given.
+// The returned handle changes the filter of the logs and exported spans while
+// the subscriber is running, query metrics are recorded whatever the filter.
 pub fn get_subscriber<Sink>(
     name: String,
     env_filter: String,
+    format: LogFormat,
+    redaction: Redaction,
     sink: Sink,
-) -> impl Subscriber + Send + Sync
+    tracer: Option<Tracer>,
+) -> (impl Subscriber + Send + Sync, LogFilter)
 where
     for<'a> Sink: MakeWriter<'a> + Send + Sync + 'static,
 {
     let env_filter =
         EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(env_filter));
+    let (env_filter, handle) = reload::Layer::new(env_filter);
 
-    let formatter_layer = BunyanFormattingLayer::new(name, sink);
-    Registry::default()
-        .with(env_filter)
-        .with(JsonStorageLayer)
-        .with(formatter_layer)
+    let formatter_layer: FormatLayer = match format {
+        LogFormat::Bunyan => Box::new(JsonStorageLayer.and_then(BunyanFormattingLayer::new(
+            name,
+            RedactingMakeWriter::new(sink, redaction),
+        ))),
+        LogFormat::Compact => Box::new(
+            tracing_subscriber::fmt::layer()
+                .compact()
+                .with_ansi(false)
+                .fmt_fields(RedactingFields::new(redaction))
+                .with_writer(sink),
+        ),
+        LogFormat::Pretty => Box::new(
+            tracing_subscriber::fmt::layer()
+                .pretty()
+                .fmt_fields(RedactingFields::new(redaction))
+                .with_writer(sink),
+        ),
+    };
+    let output_layer = formatter_layer
+        .and_then(tracer.map(|tracer| tracing_opentelemetry::layer().with_tracer(tracer)))
+        .with_filter(env_filter);
+    let metrics_layer = QueryMetricsLayer.with_filter(filter_fn(|metadata| {
+        metadata.is_span() && metadata.name().ends_with(REPOSITORY_SPAN_SUFFIX)
+    }));
+    let subscriber = Registry::default().with(output_layer).with(metrics_layer);
+    (subscriber, LogFilter::new(handle))
 }
 
 // Attach subscriber to the global logger
@@ -0,0 +1,113 @@
+use futures_util::future::BoxFuture;
+use opentelemetry::sdk::export::trace::{ExportResult, SpanData, SpanExporter};
+use opentelemetry::sdk::propagation::TraceContextPropagator;
+use opentelemetry::sdk::trace::{EvictedHashMap, EvictedQueue};
+use opentelemetry::sdk::{trace, Resource};
+use opentelemetry::trace::{TraceError, TracerProvider};
+use opentelemetry::KeyValue;
+use opentelemetry_otlp::{SpanExporterBuilder, WithExportConfig};
+
+use super::Redaction;
+use crate::{OpenTelemetrySettings, OtlpProtocol};
+
+// Build a tracer exporting spans in batches over OTLP, attributes go through the redaction rules
+pub fn get_tracer(
+    name: String,
+    settings: &OpenTelemetrySettings,
+    redaction: Redaction,
+) -> Result<trace::Tracer, TraceError> {
+    // Continue traces started upstream from their W3C traceparent header
+    opentelemetry::global::set_text_map_propagator(TraceContextPropagator::new());
+
+    let exporter: SpanExporterBuilder = match settings.protocol {
+        OtlpProtocol::Grpc => opentelemetry_otlp::new_exporter()
+            .tonic()
+            .with_endpoint(&settings.endpoint)
+            .into(),
+        OtlpProtocol::Http => opentelemetry_otlp::new_exporter()
+            .http()
+            .with_endpoint(&settings.endpoint)
+            .into(),
+    };
+    let exporter = RedactingExporter {
+        exporter: exporter.build_span_exporter()?,
+        redaction,
+    };
+    let provider = trace::TracerProvider::builder()
+        .with_batch_exporter(exporter, opentelemetry::runtime::TokioCurrentThread)
+        .with_config(
+            trace::config().with_resource(Resource::new(vec![KeyValue::new(
+                "service.name",
+                name.clone(),
+            )])),
+        )
+        .build();
+    let tracer = provider.tracer(name);
+    // Registered globally so shutdown_tracer flushes it
+    opentelemetry::global::set_tracer_provider(provider);
+    Ok(tracer)
+}
+
+// Export spans still buffered, blocks until the exporter is done
+pub fn shutdown_tracer() {
+    opentelemetry::global::shutdown_tracer_provider();
+}
+
+// Applies the redaction rules to span and event attributes before they leave the process
+#[derive(Debug)]
+struct RedactingExporter<E> {
+    exporter: E,
+    redaction: Redaction,
+}
+
+impl<E: SpanExporter> RedactingExporter<E> {
+    fn redact(&self, attribute: KeyValue) -> Option<KeyValue> {
+        let value = attribute.value.as_str();
+        match self.redaction.redact(attribute.key.as_str(), &value) {
+            Some(redacted) if redacted == value => Some(attribute),
+            Some(redacted) => Some(KeyValue::new(attribute.key, redacted)),
+            None => None,
+        }
+    }
+
+    fn redact_span(&self, mut span: SpanData) -> SpanData {
+        let mut attributes =
+            EvictedHashMap::new(span.attributes.len() as u32, span.attributes.len());
+        for (key, value) in span.attributes {
+            if let Some(attribute) = self.redact(KeyValue::new(key, value)) {
+                attributes.insert(attribute);
+            }
+        }
+        span.attributes = attributes;
+
+        // Events carry the fields of the tracing events recorded inside the span
+        let mut events = EvictedQueue::new(span.events.len() as u32);
+        events.extend(span.events.into_iter().map(|mut event| {
+            event.attributes = std::mem::take(&mut event.attributes)
+                .into_iter()
+                .filter_map(|attribute| self.redact(attribute))
+                .collect();
+            event
+        }));
+        span.events = events;
+        span
+    }
+}
+
+impl<E: SpanExporter> SpanExporter for RedactingExporter<E> {
+    fn export(&mut self, batch: Vec<SpanData>) -> BoxFuture<'static, ExportResult> {
+        let batch = batch
+            .into_iter()
+            .map(|span| self.redact_span(span))
+            .collect();
+        self.exporter.export(batch)
+    }
+
+    fn shutdown(&mut self) {
+        self.exporter.shutdown()
+    }
+
+    fn force_flush(&mut self) -> BoxFuture<'static, ExportResult> {
+        self.exporter.force_flush()
+    }
+}
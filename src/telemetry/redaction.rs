@@ -0,0 +1,214 @@
+use std::fmt;
+use std::io::{self, Write};
+use std::sync::Arc;
+
+use sha2::{Digest, Sha256};
+use tracing::field::{Field, Visit};
+use tracing_subscriber::field::RecordFields;
+use tracing_subscriber::fmt::format::Writer;
+use tracing_subscriber::fmt::{FormatFields, MakeWriter};
+
+use crate::{default_redaction_rules, RedactionAction, RedactionRule};
+
+// Characters left visible by the mask action
+const MASK_VISIBLE_CHARS: usize = 4;
+// Fields holding the request target, their query parameters are redacted too
+const URL_FIELDS: &[&str] = &["http.target"];
+
+// Rules deciding how sensitive fields are rendered
+#[derive(Clone, Debug)]
+pub struct Redaction(Arc<Vec<RedactionRule>>);
+
+// Standard rules, as used when the settings leave them out
+impl Default for Redaction {
+    fn default() -> Self {
+        Self::new(default_redaction_rules())
+    }
+}
+
+impl Redaction {
+    pub fn new(rules: Vec<RedactionRule>) -> Self {
+        Self(Arc::new(rules))
+    }
+
+    fn action_for(&self, field: &str) -> Option<RedactionAction> {
+        self.0
+            .iter()
+            .find(|rule| {
+                field == rule.field
+                    || field
+                        .strip_suffix(rule.field.as_str())
+                        .is_some_and(|prefix| prefix.ends_with('_'))
+            })
+            .map(|rule| rule.action)
+    }
+
+    // Rendered value of a field, None when it must be left out
+    pub fn redact(&self, field: &str, value: &str) -> Option<String> {
+        if URL_FIELDS.contains(&field) {
+            return Some(self.redact_query(value));
+        }
+        match self.action_for(field) {
+            None => Some(value.to_owned()),
+            Some(RedactionAction::Drop) => None,
+            Some(RedactionAction::Hash) => Some(hash(value)),
+            Some(RedactionAction::Mask) => Some(mask(value)),
+        }
+    }
+
+    // Apply the rules to query parameters, e.g. `/confirm?token=...`
+    fn redact_query(&self, target: &str) -> String {
+        let Some((path, query)) = target.split_once('?') else {
+            return target.to_owned();
+        };
+        let query: Vec<String> = query
+            .split('&')
+            .filter_map(|pair| match pair.split_once('=') {
+                Some((name, value)) => self
+                    .redact(name, value)
+                    .map(|value| format!("{}={}", name, value)),
+                None => Some(pair.to_owned()),
+            })
+            .collect();
+        format!("{}?{}", path, query.join("&"))
+    }
+}
+
+fn hash(value: &str) -> String {
+    let digest = Sha256::digest(value.as_bytes());
+    let hex: String = digest[..8]
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    format!("sha256:{}", hex)
+}
+
+fn mask(value: &str) -> String {
+    let chars: Vec<char> = value.chars().collect();
+    // Short values would be revealed entirely
+    if chars.len() <= MASK_VISIBLE_CHARS * 2 {
+        return "****".into();
+    }
+    let visible: String = chars[chars.len() - MASK_VISIBLE_CHARS..].iter().collect();
+    format!("****{}", visible)
+}
+
+// Redacts the top level fields of JSON log lines, bunyan flattens span and event fields there
+pub struct RedactingMakeWriter<M> {
+    make_writer: M,
+    redaction: Redaction,
+}
+
+impl<M> RedactingMakeWriter<M> {
+    pub fn new(make_writer: M, redaction: Redaction) -> Self {
+        Self {
+            make_writer,
+            redaction,
+        }
+    }
+}
+
+impl<'a, M: MakeWriter<'a>> MakeWriter<'a> for RedactingMakeWriter<M> {
+    type Writer = RedactingWriter<M::Writer>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        RedactingWriter {
+            writer: self.make_writer.make_writer(),
+            redaction: self.redaction.clone(),
+        }
+    }
+}
+
+pub struct RedactingWriter<W> {
+    writer: W,
+    redaction: Redaction,
+}
+
+impl<W: Write> Write for RedactingWriter<W> {
+    // Every record is written in a single call, anything that is not a JSON object passes through
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let Ok(serde_json::Value::Object(record)) = serde_json::from_slice(buf) else {
+            self.writer.write_all(buf)?;
+            return Ok(buf.len());
+        };
+        let record: serde_json::Map<String, serde_json::Value> = record
+            .into_iter()
+            .filter_map(|(key, value)| {
+                let rendered = match &value {
+                    serde_json::Value::String(value) => value.clone(),
+                    value => value.to_string(),
+                };
+                match self.redaction.redact(&key, &rendered) {
+                    Some(redacted) if redacted == rendered => Some((key, value)),
+                    Some(redacted) => Some((key, serde_json::Value::String(redacted))),
+                    None => None,
+                }
+            })
+            .collect();
+        let mut line = serde_json::to_vec(&record)?;
+        line.push(b'\n');
+        self.writer.write_all(&line)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+// Field formatter for the human readable formats, renders `message key=value ...`
+pub struct RedactingFields(Redaction);
+
+impl RedactingFields {
+    pub fn new(redaction: Redaction) -> Self {
+        Self(redaction)
+    }
+}
+
+impl<'writer> FormatFields<'writer> for RedactingFields {
+    fn format_fields<R: RecordFields>(&self, writer: Writer<'writer>, fields: R) -> fmt::Result {
+        let mut visitor = RedactingVisitor {
+            writer,
+            redaction: &self.0,
+            is_empty: true,
+            result: Ok(()),
+        };
+        fields.record(&mut visitor);
+        visitor.result
+    }
+}
+
+struct RedactingVisitor<'a, 'writer> {
+    writer: Writer<'writer>,
+    redaction: &'a Redaction,
+    is_empty: bool,
+    result: fmt::Result,
+}
+
+impl RedactingVisitor<'_, '_> {
+    fn record_value(&mut self, field: &Field, value: String) {
+        if self.result.is_err() {
+            return;
+        }
+        let separator = if self.is_empty { "" } else { " " };
+        self.result = if field.name() == "message" {
+            write!(self.writer, "{}{}", separator, value)
+        } else {
+            match self.redaction.redact(field.name(), &value) {
+                Some(value) => write!(self.writer, "{}{}={}", separator, field.name(), value),
+                None => return,
+            }
+        };
+        self.is_empty = false;
+    }
+}
+
+impl Visit for RedactingVisitor<'_, '_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.record_value(field, value.to_owned());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.record_value(field, format!("{:?}", value));
+    }
+}
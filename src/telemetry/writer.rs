@@ -0,0 +1,56 @@
+use std::io::{Error, ErrorKind, Write};
+use std::path::Path;
+
+use file_rotate::compression::Compression;
+use file_rotate::suffix::{AppendCount, AppendTimestamp, FileLimit};
+use file_rotate::{ContentLimit, FileRotate, TimeFrequency};
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
+
+use crate::{LogDestination, LogFileSettings, LogRotation, TelemetrySettings};
+
+// Writer for the configured destination, keep the guard alive to flush file logs on exit
+pub fn get_writer(
+    settings: &TelemetrySettings,
+) -> Result<(BoxMakeWriter, Option<WorkerGuard>), Error> {
+    match settings.destination {
+        LogDestination::Stdout => Ok((BoxMakeWriter::new(std::io::stdout), None)),
+        LogDestination::Stderr => Ok((BoxMakeWriter::new(std::io::stderr), None)),
+        LogDestination::File => {
+            let file_settings = settings.file.as_ref().ok_or_else(|| {
+                Error::new(
+                    ErrorKind::InvalidInput,
+                    "telemetry.file must be set when logging to a file",
+                )
+            })?;
+            // Writing to disk happens on a background thread
+            let (writer, guard) = tracing_appender::non_blocking(rotating_file(file_settings)?);
+            Ok((BoxMakeWriter::new(writer), Some(guard)))
+        }
+    }
+}
+
+// Log file rotated by size or daily, only the newest `max_files` rotated files are kept
+pub fn rotating_file(settings: &LogFileSettings) -> Result<Box<dyn Write + Send>, Error> {
+    std::fs::create_dir_all(&settings.directory)?;
+    let path = Path::new(&settings.directory).join(&settings.file_name);
+    let file: Box<dyn Write + Send> = match settings.rotation {
+        LogRotation::Size => Box::new(FileRotate::new(
+            path,
+            AppendCount::new(settings.max_files),
+            ContentLimit::BytesSurpassed(settings.max_size_bytes),
+            Compression::None,
+            #[cfg(unix)]
+            None,
+        )),
+        LogRotation::Daily => Box::new(FileRotate::new(
+            path,
+            AppendTimestamp::default(FileLimit::MaxFiles(settings.max_files)),
+            ContentLimit::Time(TimeFrequency::Daily),
+            Compression::None,
+            #[cfg(unix)]
+            None,
+        )),
+    };
+    Ok(file)
+}
@@ -0,0 +1,92 @@
+use std::str::FromStr;
+
+use chrono_tz::Tz;
+use validator::ValidationError;
+
+// BCP-47 language tags accepted for user locale
+pub const KNOWN_LOCALES: &[&str] = &[
+    "ar", "ar-EG", "ar-SA", "bg-BG", "bn-IN", "ca-ES", "cs-CZ", "da-DK", "de", "de-AT", "de-CH",
+    "de-DE", "el-GR", "en", "en-AU", "en-CA", "en-GB", "en-IE", "en-IN", "en-NZ", "en-US", "en-ZA",
+    "es", "es-AR", "es-ES", "es-MX", "es-US", "et-EE", "fa-IR", "fi-FI", "fil-PH", "fr", "fr-BE",
+    "fr-CA", "fr-CH", "fr-FR", "he-IL", "hi-IN", "hr-HR", "hu-HU", "id-ID", "it", "it-IT", "ja",
+    "ja-JP", "ko", "ko-KR", "lt-LT", "lv-LV", "ms-MY", "nb-NO", "nl", "nl-BE", "nl-NL", "pl-PL",
+    "pt", "pt-BR", "pt-PT", "ro-RO", "ru-RU", "sk-SK", "sl-SI", "sr-RS", "sv-SE", "sw-KE", "ta-IN",
+    "th-TH", "tr-TR", "uk-UA", "ur-PK", "vi-VN", "zh", "zh-CN", "zh-Hans", "zh-Hant", "zh-HK",
+    "zh-TW",
+];
+
+// Check locale against the known BCP-47 tags
+pub fn validate_locale(locale: &str) -> Result<(), ValidationError> {
+    if KNOWN_LOCALES.contains(&locale) {
+        return Ok(());
+    }
+    Err(ValidationError::new("unknown_locale"))
+}
+
+// Check timezone against the IANA timezone database
+pub fn validate_timezone(timezone: &str) -> Result<(), ValidationError> {
+    match Tz::from_str(timezone) {
+        Ok(_) => Ok(()),
+        Err(_) => Err(ValidationError::new("unknown_timezone")),
+    }
+}
+
+// Handles that could be mistaken for system accounts or routes
+pub const RESERVED_HANDLES: &[&str] = &[
+    "about",
+    "abuse",
+    "admin",
+    "administrator",
+    "api",
+    "audit",
+    "auth",
+    "avatar",
+    "help",
+    "info",
+    "login",
+    "logout",
+    "me",
+    "metrics",
+    "moderator",
+    "null",
+    "ready",
+    "root",
+    "security",
+    "settings",
+    "signup",
+    "staff",
+    "support",
+    "system",
+    "undefined",
+    "user",
+    "users",
+    "www",
+];
+
+pub const HANDLE_MIN_LENGTH: usize = 3;
+pub const HANDLE_MAX_LENGTH: usize = 30;
+
+// Check handle charset and length, letters, digits and underscores only
+pub fn validate_handle_charset(handle: &str) -> Result<(), ValidationError> {
+    let is_valid = (HANDLE_MIN_LENGTH..=HANDLE_MAX_LENGTH).contains(&handle.len())
+        && handle
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if is_valid {
+        return Ok(());
+    }
+    Err(ValidationError::new("invalid_handle"))
+}
+
+// Reserved words are matched case-insensitively like the handles themselves
+pub fn validate_handle_not_reserved(handle: &str) -> Result<(), ValidationError> {
+    if RESERVED_HANDLES.contains(&handle.to_lowercase().as_str()) {
+        return Err(ValidationError::new("reserved_handle"));
+    }
+    Ok(())
+}
+
+pub fn validate_handle(handle: &str) -> Result<(), ValidationError> {
+    validate_handle_charset(handle)?;
+    validate_handle_not_reserved(handle)
+}
@@ -0,0 +1,200 @@
+use actix_template::routes::AuditEvent;
+use image::{ImageBuffer, ImageOutputFormat, Rgb};
+use reqwest::multipart::{Form, Part};
+use reqwest::Client;
+use std::collections::HashMap;
+use std::io::Cursor;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+mod common;
+
+#[actix_web::test]
+#[serial_test::serial]
+async fn user_mutations_are_audited() -> Result<(), Box<dyn std::error::Error>> {
+    // Spawn App
+    let app = common::spawn_app().await;
+    let client = Client::new();
+
+    // Admin
+    let admin = common::create_user(&client, &app.address, "admin@gmail.com").await;
+    sqlx::query!(r#"UPDATE users SET is_admin = true WHERE id = $1"#, &admin)
+        .execute(&app.db_pool)
+        .await?;
+
+    // Anonymous creation
+    let id = common::create_user(&client, &app.address, "target@gmail.com").await;
+
+    // Update by the admin
+    let mut user_map = HashMap::new();
+    user_map.insert("name", "renamed");
+    user_map.insert("password", "new-password");
+    let response = client
+        .put(format!("{}/user/{}", &app.address, &id))
+        .basic_auth("admin@gmail.com", Some("password"))
+        // Client supplied, the peer address is recorded instead
+        .header("X-Forwarded-For", "203.0.113.9")
+        .json(&user_map)
+        .send()
+        .await?;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // Wrong credentials are rejected rather than treated as anonymous
+    let response = client
+        .delete(format!("{}/user/{}", &app.address, &id))
+        .basic_auth("admin@gmail.com", Some("wrong"))
+        .send()
+        .await?;
+    assert_eq!(response.status().as_u16(), 401);
+
+    // Delete
+    let response = client
+        .delete(format!("{}/user/{}", &app.address, &id))
+        .basic_auth("admin@gmail.com", Some("password"))
+        .send()
+        .await?;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // Query audit log
+    let response = client
+        .get(format!("{}/audit?target={}", &app.address, &id))
+        .basic_auth("admin@gmail.com", Some("password"))
+        .send()
+        .await?;
+    assert_eq!(response.status().as_u16(), 200);
+    let body = response.text().await?;
+    // Password values are never recorded
+    assert!(!body.contains("new-password"));
+    let events: Vec<AuditEvent> = serde_json::from_str(&body)?;
+    let actions: Vec<&str> = events.iter().map(|e| e.action.as_str()).collect();
+    assert_eq!(actions, ["user.deleted", "user.updated", "user.created"]);
+    assert!(events.iter().all(|e| e.target_id == id));
+    assert!(events.iter().all(|e| e.request_id.is_some()));
+    assert!(events
+        .iter()
+        .all(|e| e.ip_address.as_deref() == Some("127.0.0.1")));
+    assert_eq!(events[0].actor_id, Some(admin));
+    assert_eq!(events[1].actor_id, Some(admin));
+    assert_eq!(events[1].changed_fields, ["name", "password"]);
+    assert_eq!(events[2].actor_id, None);
+
+    // Filter by actor
+    let events = client
+        .get(format!("{}/audit?actor={}", &app.address, &admin))
+        .basic_auth("admin@gmail.com", Some("password"))
+        .send()
+        .await?
+        .json::<Vec<AuditEvent>>()
+        .await?;
+    assert_eq!(events.len(), 2);
+
+    // Filter by time range
+    let events = client
+        .get(format!("{}/audit", &app.address))
+        .query(&[
+            ("from", "2000-01-01T00:00:00Z"),
+            ("to", "2000-01-02T00:00:00Z"),
+        ])
+        .basic_auth("admin@gmail.com", Some("password"))
+        .send()
+        .await?
+        .json::<Vec<AuditEvent>>()
+        .await?;
+    assert!(events.is_empty());
+
+    Ok(())
+}
+
+#[actix_web::test]
+#[serial_test::serial]
+async fn email_and_avatar_changes_are_audited() -> Result<(), Box<dyn std::error::Error>> {
+    // Spawn App
+    let app = common::spawn_app().await;
+    let client = Client::new();
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+    let admin = common::create_user(&client, &app.address, "admin@gmail.com").await;
+    sqlx::query!(r#"UPDATE users SET is_admin = true WHERE id = $1"#, &admin)
+        .execute(&app.db_pool)
+        .await?;
+    let id = common::create_user(&client, &app.address, "old@gmail.com").await;
+
+    // Email change, confirmed with the token sent to the new address
+    let mut email_map = HashMap::new();
+    email_map.insert("email", "new@gmail.com");
+    let response = client
+        .post(format!("{}/user/{}/email", &app.address, &id))
+        .basic_auth("old@gmail.com", Some("password"))
+        .json(&email_map)
+        .send()
+        .await?;
+    assert_eq!(response.status().as_u16(), 202);
+    let request = sqlx::query!(
+        r#"SELECT token FROM email_change_requests WHERE user_id = $1"#,
+        &id
+    )
+    .fetch_one(&app.db_pool)
+    .await?;
+    let response = client
+        .get(format!("{}/user/email/confirm", &app.address))
+        .query(&[("token", request.token)])
+        .send()
+        .await?;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // Avatar upload by the owner
+    let image = ImageBuffer::from_pixel(10, 10, Rgb([200u8, 40, 40]));
+    let mut png = Cursor::new(Vec::new());
+    image.write_to(&mut png, ImageOutputFormat::Png)?;
+    let part = Part::bytes(png.into_inner())
+        .file_name("avatar")
+        .mime_str("image/png")?;
+    let response = client
+        .put(format!("{}/user/{}/avatar", &app.address, &id))
+        .basic_auth("new@gmail.com", Some("password"))
+        .multipart(Form::new().part("avatar", part))
+        .send()
+        .await?;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // Newest first
+    let events = client
+        .get(format!("{}/audit?target={}", &app.address, &id))
+        .basic_auth("admin@gmail.com", Some("password"))
+        .send()
+        .await?
+        .json::<Vec<AuditEvent>>()
+        .await?;
+    let actions: Vec<&str> = events.iter().map(|e| e.action.as_str()).collect();
+    assert_eq!(actions, ["user.updated", "user.updated", "user.created"]);
+    assert_eq!(events[0].changed_fields, ["avatar_url"]);
+    assert_eq!(events[0].actor_id, Some(id));
+    assert_eq!(events[1].changed_fields, ["email"]);
+    assert!(events.iter().all(|e| e.request_id.is_some()));
+
+    Ok(())
+}
+
+#[actix_web::test]
+#[serial_test::serial]
+async fn audit_log_is_admin_only() -> Result<(), Box<dyn std::error::Error>> {
+    // Spawn App
+    let app = common::spawn_app().await;
+    let client = Client::new();
+    common::create_user(&client, &app.address, "user@gmail.com").await;
+
+    let response = client.get(format!("{}/audit", &app.address)).send().await?;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = client
+        .get(format!("{}/audit", &app.address))
+        .basic_auth("user@gmail.com", Some("password"))
+        .send()
+        .await?;
+    assert_eq!(response.status().as_u16(), 403);
+
+    Ok(())
+}
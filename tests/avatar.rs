@@ -0,0 +1,182 @@
+use actix_template::routes::GetUser;
+use image::{ImageBuffer, ImageOutputFormat, Rgb};
+use reqwest::{
+    multipart::{Form, Part},
+    Client,
+};
+use std::io::Cursor;
+use uuid::Uuid;
+
+mod common;
+
+fn png_image(width: u32, height: u32) -> Vec<u8> {
+    let image = ImageBuffer::from_pixel(width, height, Rgb([200u8, 40, 40]));
+    let mut bytes = Cursor::new(Vec::new());
+    image
+        .write_to(&mut bytes, ImageOutputFormat::Png)
+        .expect("Failed to encode image.");
+    bytes.into_inner()
+}
+
+fn avatar_form(bytes: Vec<u8>, mime: &str) -> Form {
+    let part = Part::bytes(bytes)
+        .file_name("avatar")
+        .mime_str(mime)
+        .unwrap();
+    Form::new().part("avatar", part)
+}
+
+#[actix_web::test]
+#[serial_test::serial]
+async fn avatar_upload_and_thumbnails() -> Result<(), Box<dyn std::error::Error>> {
+    // Spawn App
+    let app = common::spawn_app().await;
+    let client = Client::new();
+    let id = common::create_user(&client, &app.address, "avatar@gmail.com").await;
+
+    // Upload avatar
+    let original = png_image(300, 200);
+    let response = client
+        .put(format!("{}/user/{}/avatar", &app.address, &id))
+        .basic_auth("avatar@gmail.com", Some("password"))
+        .multipart(avatar_form(original.clone(), "image/png"))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status().as_u16(), 200);
+
+    // Avatar url is exposed through GetUser
+    let user = client
+        .get(format!("{}/user/{}", &app.address, &id))
+        .send()
+        .await?
+        .json::<GetUser>()
+        .await?;
+    let avatar_url = format!("/user/{}/avatar", id);
+    assert_eq!(user.avatar_url.as_deref(), Some(avatar_url.as_str()));
+
+    // Original is served back unchanged
+    let response = client
+        .get(format!("{}{}", &app.address, avatar_url))
+        .send()
+        .await?;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.headers()["content-type"], "image/png");
+    assert_eq!(response.bytes().await?.as_ref(), original.as_slice());
+
+    // Thumbnails are square PNGs
+    for size in [64u32, 256] {
+        let response = client
+            .get(format!("{}{}?size={}", &app.address, avatar_url, size))
+            .send()
+            .await?;
+        assert_eq!(response.status().as_u16(), 200);
+        let thumbnail = image::load_from_memory(&response.bytes().await?)?;
+        assert_eq!((thumbnail.width(), thumbnail.height()), (size, size));
+    }
+
+    // Unknown thumbnail size
+    let response = client
+        .get(format!("{}{}?size=10", &app.address, avatar_url))
+        .send()
+        .await?;
+    assert_eq!(response.status().as_u16(), 400);
+
+    Ok(())
+}
+
+#[actix_web::test]
+#[serial_test::serial]
+async fn avatar_upload_is_validated() -> Result<(), Box<dyn std::error::Error>> {
+    // Spawn App
+    let app = common::spawn_app().await;
+    let client = Client::new();
+    let id = common::create_user(&client, &app.address, "avatar@gmail.com").await;
+
+    // Type is detected from the content, not the declared MIME type
+    let response = client
+        .put(format!("{}/user/{}/avatar", &app.address, &id))
+        .basic_auth("avatar@gmail.com", Some("password"))
+        .multipart(avatar_form(b"not an image".to_vec(), "image/png"))
+        .send()
+        .await?;
+    assert_eq!(response.status().as_u16(), 415);
+
+    // Larger than storage.max_avatar_size
+    let mut oversized = png_image(1, 1);
+    oversized.resize(6 * 1024 * 1024, 0);
+    let response = client
+        .put(format!("{}/user/{}/avatar", &app.address, &id))
+        .basic_auth("avatar@gmail.com", Some("password"))
+        .multipart(avatar_form(oversized, "image/png"))
+        .send()
+        .await;
+    // The server may close the connection before the whole body is sent
+    if let Ok(response) = response {
+        assert_eq!(response.status().as_u16(), 413);
+    }
+
+    // Unknown user, only an admin gets past the ownership check
+    sqlx::query!(r#"UPDATE users SET is_admin = true WHERE id = $1"#, &id)
+        .execute(&app.db_pool)
+        .await?;
+    let response = client
+        .put(format!("{}/user/{}/avatar", &app.address, Uuid::new_v4()))
+        .basic_auth("avatar@gmail.com", Some("password"))
+        .multipart(avatar_form(png_image(10, 10), "image/png"))
+        .send()
+        .await?;
+    assert_eq!(response.status().as_u16(), 404);
+
+    // No avatar uploaded yet
+    let response = client
+        .get(format!("{}/user/{}/avatar", &app.address, &id))
+        .send()
+        .await?;
+    assert_eq!(response.status().as_u16(), 404);
+
+    Ok(())
+}
+
+#[actix_web::test]
+#[serial_test::serial]
+async fn avatars_can_only_be_uploaded_by_the_owner() -> Result<(), Box<dyn std::error::Error>> {
+    // Spawn App
+    let app = common::spawn_app().await;
+    let client = Client::new();
+    let id = common::create_user(&client, &app.address, "avatar@gmail.com").await;
+    common::create_user(&client, &app.address, "other@gmail.com").await;
+
+    // Anonymous
+    let response = client
+        .put(format!("{}/user/{}/avatar", &app.address, &id))
+        .multipart(avatar_form(png_image(10, 10), "image/png"))
+        .send()
+        .await?;
+    assert_eq!(response.status().as_u16(), 401);
+
+    // Another user
+    let response = client
+        .put(format!("{}/user/{}/avatar", &app.address, &id))
+        .basic_auth("other@gmail.com", Some("password"))
+        .multipart(avatar_form(png_image(10, 10), "image/png"))
+        .send()
+        .await?;
+    assert_eq!(response.status().as_u16(), 403);
+
+    // Avatar is left unchanged
+    let user = client
+        .get(format!("{}/user/{}", &app.address, &id))
+        .send()
+        .await?
+        .json::<GetUser>()
+        .await?;
+    assert!(user.avatar_url.is_none());
+    let response = client
+        .get(format!("{}/user/{}/avatar", &app.address, &id))
+        .send()
+        .await?;
+    assert_eq!(response.status().as_u16(), 404);
+
+    Ok(())
+}
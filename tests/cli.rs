@@ -0,0 +1,295 @@
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+use actix_template::get_configuration;
+use secrecy::ExposeSecret;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+mod common;
+use common::{copy_configuration_directory, write_configuration_file};
+
+// Run the binary from a directory holding `configuration/`
+fn run(working_directory: &std::path::Path, arguments: &[&str]) -> Output {
+    run_with(working_directory, arguments, None, "")
+}
+
+// Run the binary against `database_url`, writing `stdin` to it
+fn run_with(
+    working_directory: &std::path::Path,
+    arguments: &[&str],
+    database_url: Option<&str>,
+    stdin: &str,
+) -> Output {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_actix-template"));
+    command
+        .args(arguments)
+        .current_dir(working_directory)
+        .env_remove("DATABASE_URL")
+        .env_remove("APP_ENVIRONMENT")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    if let Some(database_url) = database_url {
+        command.env("DATABASE_URL", database_url);
+    }
+    let mut child = command.spawn().expect("Failed to run the binary.");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+// URL of a database that does not exist yet
+fn new_database_url() -> String {
+    let database = get_configuration()
+        .expect("Failed to read configuration.")
+        .database;
+    format!(
+        "postgres://{}:{}@{}:{}/{}",
+        database.username,
+        database.password.expose_secret(),
+        database.host,
+        database.port,
+        Uuid::new_v4()
+    )
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8(output.stderr.clone()).unwrap()
+}
+
+fn working_directory() -> std::path::PathBuf {
+    let configuration = copy_configuration_directory();
+    let working_directory = configuration
+        .parent()
+        .unwrap()
+        .join(uuid::Uuid::new_v4().to_string());
+    std::fs::create_dir_all(&working_directory).unwrap();
+    std::fs::rename(configuration, working_directory.join("configuration")).unwrap();
+    working_directory
+}
+
+#[test]
+fn config_show_masks_secrets_and_names_origins() {
+    // Arrange
+    let directory = working_directory();
+
+    // Act
+    let output = run(
+        &directory,
+        &["config", "show", "--environment", "production"],
+    );
+
+    // Assert
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("application.host_address = 0.0.0.0  # configuration/production.yaml"));
+    assert!(
+        stdout.contains("email_client.authorization_token = ********  # configuration/base.yaml")
+    );
+    assert!(stdout.contains("environment = production"));
+    assert!(!stdout.contains("my-secret-token"));
+}
+
+#[test]
+fn config_check_exits_non_zero_on_invalid_configuration() {
+    // Arrange
+    let directory = working_directory();
+
+    // Act
+    let valid = run(&directory, &["config", "check"]);
+    write_configuration_file(
+        &directory.join("configuration"),
+        "local.yaml",
+        "database:\n  pool:\n    max_connections: 0\n",
+    );
+    let invalid = run(&directory, &["config", "check"]);
+
+    // Assert
+    assert!(valid.status.success());
+    assert_eq!(invalid.status.code(), Some(1));
+    let stderr = String::from_utf8(invalid.stderr).unwrap();
+    assert!(
+        stderr.contains("database.pool.max_connections: must be greater than 0"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn migrate_up_creates_the_database_and_applies_every_migration() {
+    // Arrange
+    let directory = working_directory();
+    let database_url = new_database_url();
+    let migrate =
+        |command: &str| run_with(&directory, &["migrate", command], Some(&database_url), "");
+
+    // Act
+    let up = migrate("up");
+    let status = migrate("status");
+
+    // Assert
+    assert!(up.status.success(), "{}", stderr(&up));
+    assert!(stdout(&up).contains("Created database"));
+    assert!(stdout(&up).contains("Applied 20230402193235 create users table"));
+    assert!(status.status.success(), "{}", stderr(&status));
+    assert!(stdout(&status).contains("20230506110000 applied create audit events table"));
+    assert!(!stdout(&status).contains("pending"));
+}
+
+#[test]
+fn migrate_down_reverts_the_latest_migration() {
+    // Arrange
+    let directory = working_directory();
+    let database_url = new_database_url();
+    let migrate =
+        |command: &str| run_with(&directory, &["migrate", command], Some(&database_url), "");
+    let up = migrate("up");
+    assert!(up.status.success(), "{}", stderr(&up));
+
+    // Act
+    let down = migrate("down");
+    let status = migrate("status");
+
+    // Assert
+    assert!(down.status.success(), "{}", stderr(&down));
+    assert!(stdout(&down).contains("Reverted 20230506110000 create audit events table"));
+    assert!(stdout(&status).contains("20230506110000 pending create audit events table"));
+    assert!(stdout(&status).contains("20230429143000 applied"));
+}
+
+#[test]
+fn migrate_check_reports_drift_from_the_query_data() {
+    // Arrange
+    let directory = working_directory();
+    let database_url = new_database_url();
+    let query_data = concat!(env!("CARGO_MANIFEST_DIR"), "/.sqlx");
+    let run_migrate = |arguments: &[&str]| {
+        let arguments = [&["migrate"], arguments].concat();
+        run_with(&directory, &arguments, Some(&database_url), "")
+    };
+    let up = run_migrate(&["up"]);
+    assert!(up.status.success(), "{}", stderr(&up));
+
+    // Act
+    let current = run_migrate(&["check", "--query-data", query_data]);
+    let down = run_migrate(&["down"]);
+    assert!(down.status.success(), "{}", stderr(&down));
+    let drifted = run_migrate(&["check", "--query-data", query_data]);
+
+    // Assert
+    assert!(current.status.success(), "{}", stderr(&current));
+    assert!(stdout(&current).contains("No schema drift"));
+    assert_eq!(drifted.status.code(), Some(1));
+    assert!(stdout(&drifted).contains(r#"relation "audit_events" does not exist"#));
+    assert!(stderr(&drifted).contains("Schema drift in"));
+}
+
+#[actix_web::test]
+async fn create_admin_creates_a_privileged_user() {
+    // Arrange
+    let directory = working_directory();
+    let database_url = new_database_url();
+    let up = run_with(&directory, &["migrate", "up"], Some(&database_url), "");
+    assert!(up.status.success(), "{}", stderr(&up));
+    let arguments = [
+        "create-admin",
+        "--email",
+        "root@example.com",
+        "--password-stdin",
+    ];
+
+    // Act
+    let created = run_with(&directory, &arguments, Some(&database_url), "password123\n");
+    let duplicate = run_with(&directory, &arguments, Some(&database_url), "password123\n");
+    let short_password = run_with(
+        &directory,
+        &[
+            "create-admin",
+            "--email",
+            "other@example.com",
+            "--password-stdin",
+        ],
+        Some(&database_url),
+        "short\n",
+    );
+
+    // Assert
+    assert!(created.status.success(), "{}", stderr(&created));
+    assert_eq!(duplicate.status.code(), Some(1));
+    assert!(stderr(&duplicate).contains("already exists"));
+    assert_eq!(short_password.status.code(), Some(1));
+    let db_pool = PgPool::connect(&database_url).await.unwrap();
+    let (is_admin, password): (bool, String) =
+        sqlx::query_as("SELECT is_admin, password FROM users WHERE email = 'root@example.com'")
+            .fetch_one(&db_pool)
+            .await
+            .unwrap();
+    assert!(is_admin);
+    assert!(password.starts_with("$argon2id$"));
+}
+
+#[actix_web::test]
+async fn seed_inserts_fixture_users_once() {
+    // Arrange
+    let directory = working_directory();
+    let database_url = new_database_url();
+    let up = run_with(&directory, &["migrate", "up"], Some(&database_url), "");
+    assert!(up.status.success(), "{}", stderr(&up));
+
+    // Act
+    let first = run_with(&directory, &["seed"], Some(&database_url), "");
+    let second = run_with(&directory, &["seed"], Some(&database_url), "");
+    let production = run_with(
+        &directory,
+        &["seed", "--environment", "production"],
+        Some(&database_url),
+        "",
+    );
+    // Any environment but development and test needs --force
+    let configuration = directory.join("configuration");
+    std::fs::copy(
+        configuration.join("development.yaml"),
+        configuration.join("staging.yaml"),
+    )
+    .unwrap();
+    let staging = run_with(
+        &directory,
+        &["seed", "--environment", "staging"],
+        Some(&database_url),
+        "",
+    );
+    let forced = run_with(
+        &directory,
+        &["seed", "--environment", "staging", "--force"],
+        Some(&database_url),
+        "",
+    );
+
+    // Assert
+    assert!(first.status.success(), "{}", stderr(&first));
+    assert!(stdout(&first).contains("Inserted 3 fixture users"));
+    assert!(stdout(&second).contains("Inserted 0 fixture users"));
+    assert_eq!(production.status.code(), Some(1));
+    assert_eq!(staging.status.code(), Some(1));
+    assert!(
+        stderr(&staging).contains("Refusing to seed fixture users in the staging environment"),
+        "{}",
+        stderr(&staging)
+    );
+    assert!(forced.status.success(), "{}", stderr(&forced));
+    let db_pool = PgPool::connect(&database_url).await.unwrap();
+    let admins: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM users WHERE is_admin")
+        .fetch_one(&db_pool)
+        .await
+        .unwrap();
+    assert_eq!(admins, 1);
+}
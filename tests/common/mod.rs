@@ -1,42 +1,159 @@
-use actix_template::telemetry::init_subscriber;
-use actix_template::{get_configuration, telemetry, DatabaseSettings};
+// Not every test binary uses every helper or field
+#![allow(dead_code)]
+
+use actix_template::shutdown::ServerHandle;
+use actix_template::telemetry::{init_subscriber, LogFilter, Redaction};
+use actix_template::{
+    get_configuration, telemetry, Application, DatabaseSettings, LogFormat, Settings,
+};
 use once_cell::sync::Lazy;
+use reqwest::Client;
 use sqlx::{Executor, PgPool};
-use std::net::TcpListener;
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 use uuid::Uuid;
+use wiremock::MockServer;
 pub struct TestApp {
     pub address: String,
+    pub port: u16,
+    pub admin_address: String,
     pub db_pool: PgPool,
+    // Stands in for the email API
+    pub email_server: MockServer,
+    // Triggers shutdown deterministically
+    pub server_handle: ServerHandle,
+    // Filter of the global subscriber, shared by every app of the test binary
+    pub log_filter: LogFilter,
 }
 // Ensure that the `tracing` stack is only initialised once using `once_cell`
-static TRACING: Lazy<()> = Lazy::new(|| {
+static TRACING: Lazy<LogFilter> = Lazy::new(|| {
     let subscriber_name = "actix-template-test".to_string();
     let subscriber_filter = "debug".to_string();
 
     // Do not show the `tracing` logs in the test output unless env var `TEST_LOG` is set
     if std::env::var("TEST_LOG").is_ok() {
-        let subscriber =
-            telemetry::get_subscriber(subscriber_name, subscriber_filter, std::io::stdout);
+        let (subscriber, log_filter) = telemetry::get_subscriber(
+            subscriber_name,
+            subscriber_filter,
+            LogFormat::Bunyan,
+            Redaction::default(),
+            || CapturedWriter(std::io::stdout()),
+            None,
+        );
         init_subscriber(subscriber);
+        log_filter
     } else {
-        let subscriber =
-            telemetry::get_subscriber(subscriber_name, subscriber_filter, std::io::sink);
+        let (subscriber, log_filter) = telemetry::get_subscriber(
+            subscriber_name,
+            subscriber_filter,
+            LogFormat::Bunyan,
+            Redaction::default(),
+            || CapturedWriter(std::io::sink()),
+            None,
+        );
         init_subscriber(subscriber);
+        log_filter
     }
 });
 
+// Everything written by the test subscriber, so tests can check what ends up in the logs
+static CAPTURED_LOGS: Mutex<Vec<u8>> = Mutex::new(Vec::new());
+
+// Keeps a copy of the logs before passing them on
+struct CapturedWriter<W>(W);
+
+impl<W: Write> Write for CapturedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        CAPTURED_LOGS.lock().unwrap().extend_from_slice(buf);
+        self.0.write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.0.flush()
+    }
+}
+
+// Logs written so far by every app of the test binary
+pub fn captured_logs() -> String {
+    String::from_utf8_lossy(&CAPTURED_LOGS.lock().unwrap()).into_owned()
+}
+
+// Filter of the test subscriber, initialised on first use
+pub fn test_log_filter() -> LogFilter {
+    Lazy::force(&TRACING).clone()
+}
+
 pub async fn spawn_app() -> TestApp {
+    spawn_app_with(|_| {}).await
+}
+
+// Spawn the app with settings adjusted after the test database is set up
+pub async fn spawn_app_with(configure: impl FnOnce(&mut Settings)) -> TestApp {
     // The first time `initialize` is invoked the code in `TRACING` is executed.
     // All other invocations will instead skip execution.
-    Lazy::force(&TRACING);
-    let listener = TcpListener::bind("127.0.0.1:0").expect("Failed to bind random port");
-    let address = format!("http://{}", listener.local_addr().unwrap());
+    spawn_app_with_own_tracing(test_log_filter(), configure).await
+}
+
+// Spawn the app for tests that install their own global subscriber
+pub async fn spawn_app_with_own_tracing(
+    log_filter: LogFilter,
+    configure: impl FnOnce(&mut Settings),
+) -> TestApp {
     let mut configuration = get_configuration().expect("Failed to read configuration.");
+    // Use a different database for each test case
     configuration.database.database_name = Uuid::new_v4().to_string();
+    // Use a random OS port
+    configuration.application.port = 0;
+    configuration.application.admin_port = 0;
+    // Keep uploaded blobs of every test app apart
+    configuration.storage.local_path = std::env::temp_dir()
+        .join("actix-template-test")
+        .join(Uuid::new_v4().to_string())
+        .to_string_lossy()
+        .into();
+    // Send emails to the mock server
+    let email_server = MockServer::start().await;
+    configuration.email_client.base_url = email_server.uri();
+
     let db_pool = configure_test_database(&configuration.database).await;
-    let server = actix_template::run(listener, db_pool.clone()).expect("Failed to bind address");
-    actix::spawn(server);
-    TestApp { address, db_pool }
+    configure(&mut configuration);
+    let application = Application::build(configuration.clone(), log_filter.clone())
+        .await
+        .expect("Failed to build application.");
+    let port = application.port();
+    let admin_address = format!("http://127.0.0.1:{}", application.admin_port());
+    let server_handle = application.server_handle();
+    let address = format!("http://127.0.0.1:{}", port);
+    actix::spawn(application.run_until_stopped());
+    TestApp {
+        address,
+        port,
+        admin_address,
+        db_pool,
+        email_server,
+        server_handle,
+        log_filter,
+    }
+}
+
+// Sign up a user with the password `password`, returns its id
+pub async fn create_user(client: &Client, address: &str, email: &str) -> Uuid {
+    let mut user_map = HashMap::new();
+    user_map.insert("name", "test");
+    user_map.insert("email", email);
+    user_map.insert("password", "password");
+    user_map.insert("password_confirmation", "password");
+    client
+        .post(format!("{}/user/", address))
+        .json(&user_map)
+        .send()
+        .await
+        .expect("Failed to execute request.")
+        .json::<Uuid>()
+        .await
+        .expect("Failed to get response id")
 }
 
 async fn configure_test_database(configuration: &DatabaseSettings) -> PgPool {
@@ -57,3 +174,29 @@ async fn configure_test_database(configuration: &DatabaseSettings) -> PgPool {
         .expect("Failed to migrate database.");
     test_db_pool
 }
+
+// Copy of the checked in configuration directory that tests can add files to
+pub fn copy_configuration_directory() -> PathBuf {
+    let directory = std::env::temp_dir()
+        .join("actix-template-test")
+        .join(Uuid::new_v4().to_string());
+    std::fs::create_dir_all(&directory).unwrap();
+    for entry in std::fs::read_dir("configuration").unwrap() {
+        let path = entry.unwrap().path();
+        if path.file_name().unwrap() != "local.yaml" {
+            std::fs::copy(&path, directory.join(path.file_name().unwrap())).unwrap();
+        }
+    }
+    directory
+}
+
+pub fn write_configuration_file(directory: &Path, name: &str, content: &str) {
+    std::fs::write(directory.join(name), content).unwrap();
+}
+
+// Whether `needle` appears anywhere in `haystack`, e.g. a name in an exported protobuf payload
+pub fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack
+        .windows(needle.len())
+        .any(|window| window == needle)
+}
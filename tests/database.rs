@@ -0,0 +1,50 @@
+use actix_template::{get_configuration, Application};
+use uuid::Uuid;
+
+mod common;
+use common::{spawn_app_with, test_log_filter};
+
+#[actix_web::test]
+#[serial_test::serial]
+async fn connection_settings_are_applied_to_the_pool() {
+    // Arrange
+    let application_name = format!("actix-template-{}", Uuid::new_v4().simple());
+    let name = application_name.clone();
+    let app = spawn_app_with(move |configuration| {
+        configuration.database.application_name = name;
+        configuration.database.pool.min_connections = 1;
+        configuration.database.pool.connect_eagerly = true;
+    })
+    .await;
+
+    // Act
+    let connections: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM pg_stat_activity WHERE application_name = $1")
+            .bind(&application_name)
+            .fetch_one(&app.db_pool)
+            .await
+            .expect("Failed to query pg_stat_activity.");
+
+    // Assert
+    assert!(connections >= 1);
+}
+
+#[actix_web::test]
+#[serial_test::serial]
+async fn eager_connect_fails_at_startup_when_the_database_is_unreachable() {
+    // Arrange
+    let mut configuration = get_configuration().expect("Failed to read configuration.");
+    configuration.application.port = 0;
+    configuration.application.admin_port = 0;
+    // Nothing listens on port 1
+    configuration.database.port = 1;
+    configuration.database.pool.connect_eagerly = true;
+    configuration.database.pool.acquire_timeout_milliseconds = 500;
+
+    // Act
+    let result = Application::build(configuration, test_log_filter()).await;
+
+    // Assert
+    let error = result.err().expect("Build should fail.").to_string();
+    assert!(error.contains("Failed to connect to database"), "{}", error);
+}
@@ -0,0 +1,222 @@
+use reqwest::Client;
+use std::collections::HashMap;
+use uuid::Uuid;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+mod common;
+
+// Request to change the email of the user `id`, signed in as `signed_in_as`
+async fn request_change(
+    client: &Client,
+    address: &str,
+    signed_in_as: &str,
+    id: &Uuid,
+    email: &str,
+) -> u16 {
+    let mut email_map = HashMap::new();
+    email_map.insert("email", email);
+    client
+        .post(format!("{}/user/{}/email", address, id))
+        .basic_auth(signed_in_as, Some("password"))
+        .json(&email_map)
+        .send()
+        .await
+        .expect("Failed to execute request.")
+        .status()
+        .as_u16()
+}
+
+// Body of the last email sent to `recipient`
+async fn last_email_to(app: &common::TestApp, recipient: &str) -> serde_json::Value {
+    let requests = app.email_server.received_requests().await.unwrap();
+    requests
+        .iter()
+        .rev()
+        .map(|request| serde_json::from_slice::<serde_json::Value>(&request.body).unwrap())
+        .find(|body| body["To"] == recipient)
+        .expect("No email sent to recipient")
+}
+
+// Extract the confirmation link from the last email sent to `recipient`
+async fn confirmation_link(app: &common::TestApp, recipient: &str) -> String {
+    let body = last_email_to(app, recipient).await;
+    let text = body["TextBody"].as_str().unwrap();
+    let start = text.find("http").expect("No link in email");
+    let mut link = reqwest::Url::parse(text[start..].split_whitespace().next().unwrap()).unwrap();
+    // Links use the configured base url, point them at the test app
+    link.set_port(Some(app.port)).unwrap();
+    link.to_string()
+}
+
+#[actix_web::test]
+#[serial_test::serial]
+async fn change_email_requires_confirmation() -> Result<(), Box<dyn std::error::Error>> {
+    // Spawn App
+    let app = common::spawn_app().await;
+    let client = Client::new();
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+    let id = common::create_user(&client, &app.address, "old@gmail.com").await;
+
+    // Request change, email is not swapped yet
+    assert_eq!(
+        request_change(&client, &app.address, "old@gmail.com", &id, "new@gmail.com").await,
+        202
+    );
+    let user = sqlx::query!(r#"SELECT email FROM users WHERE id = $1"#, &id)
+        .fetch_one(&app.db_pool)
+        .await?;
+    assert_eq!(user.email, "old@gmail.com");
+
+    // Confirm through the link sent to the new address
+    let link = confirmation_link(&app, "new@gmail.com").await;
+    let response = client.get(&link).send().await?;
+    assert_eq!(response.status().as_u16(), 200);
+    let user = sqlx::query!(r#"SELECT email FROM users WHERE id = $1"#, &id)
+        .fetch_one(&app.db_pool)
+        .await?;
+    assert_eq!(user.email, "new@gmail.com");
+
+    // Old address is notified
+    let notification = last_email_to(&app, "old@gmail.com").await;
+    assert!(notification["TextBody"]
+        .as_str()
+        .unwrap()
+        .contains("new@gmail.com"));
+
+    // Token cannot be used twice
+    let response = client.get(&link).send().await?;
+    assert_eq!(response.status().as_u16(), 404);
+
+    // Invalid email
+    assert_eq!(
+        request_change(&client, &app.address, "new@gmail.com", &id, "not-an-email").await,
+        400
+    );
+
+    // Both addresses only reach the logs hashed
+    let logs = common::captured_logs();
+    assert!(logs.contains("[SEND EMAIL - START]"));
+    assert!(!logs.contains("old@gmail.com"));
+    assert!(!logs.contains("new@gmail.com"));
+    Ok(())
+}
+
+#[actix_web::test]
+#[serial_test::serial]
+async fn change_email_respects_unique_email() -> Result<(), Box<dyn std::error::Error>> {
+    // Spawn App
+    let app = common::spawn_app().await;
+    let client = Client::new();
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+    let first = common::create_user(&client, &app.address, "first@gmail.com").await;
+    let second = common::create_user(&client, &app.address, "second@gmail.com").await;
+
+    // Address of an existing user
+    assert_eq!(
+        request_change(
+            &client,
+            &app.address,
+            "first@gmail.com",
+            &first,
+            "second@gmail.com"
+        )
+        .await,
+        409
+    );
+
+    // Both request the same free address, only the first confirmation wins
+    assert_eq!(
+        request_change(
+            &client,
+            &app.address,
+            "first@gmail.com",
+            &first,
+            "shared@gmail.com"
+        )
+        .await,
+        202
+    );
+    let first_link = confirmation_link(&app, "shared@gmail.com").await;
+    assert_eq!(
+        request_change(
+            &client,
+            &app.address,
+            "second@gmail.com",
+            &second,
+            "shared@gmail.com"
+        )
+        .await,
+        202
+    );
+    let second_link = confirmation_link(&app, "shared@gmail.com").await;
+    assert_ne!(first_link, second_link);
+
+    let response = client.get(&second_link).send().await?;
+    assert_eq!(response.status().as_u16(), 200);
+    let response = client.get(&first_link).send().await?;
+    assert_eq!(response.status().as_u16(), 409);
+
+    // Unknown user, only an admin gets past the ownership check
+    sqlx::query!(r#"UPDATE users SET is_admin = true WHERE id = $1"#, &first)
+        .execute(&app.db_pool)
+        .await?;
+    assert_eq!(
+        request_change(
+            &client,
+            &app.address,
+            "first@gmail.com",
+            &Uuid::new_v4(),
+            "ghost@gmail.com"
+        )
+        .await,
+        404
+    );
+    Ok(())
+}
+
+#[actix_web::test]
+#[serial_test::serial]
+async fn change_email_is_limited_to_the_account_owner() -> Result<(), Box<dyn std::error::Error>> {
+    // Spawn App
+    let app = common::spawn_app().await;
+    let client = Client::new();
+    let victim = common::create_user(&client, &app.address, "victim@gmail.com").await;
+    common::create_user(&client, &app.address, "attacker@gmail.com").await;
+
+    // Anonymous
+    let response = client
+        .post(format!("{}/user/{}/email", &app.address, &victim))
+        .json(&HashMap::from([("email", "attacker-new@gmail.com")]))
+        .send()
+        .await?;
+    assert_eq!(response.status().as_u16(), 401);
+
+    // Another user
+    assert_eq!(
+        request_change(
+            &client,
+            &app.address,
+            "attacker@gmail.com",
+            &victim,
+            "attacker-new@gmail.com"
+        )
+        .await,
+        403
+    );
+    assert!(app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .is_empty());
+    Ok(())
+}
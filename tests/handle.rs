@@ -0,0 +1,125 @@
+use actix_template::routes::{GetUser, HandleAvailability, HandleUnavailableReason};
+use reqwest::{redirect::Policy, Client};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+mod common;
+
+// Set the handle of the user `id`, signed in as `email`
+async fn set_handle(client: &Client, address: &str, id: &Uuid, email: &str, handle: &str) -> u16 {
+    let mut handle_map = HashMap::new();
+    handle_map.insert("handle", handle);
+    client
+        .put(format!("{}/user/{}", address, id))
+        .basic_auth(email, Some("password"))
+        .json(&handle_map)
+        .send()
+        .await
+        .expect("Failed to execute request.")
+        .status()
+        .as_u16()
+}
+
+async fn availability(client: &Client, address: &str, handle: &str) -> HandleAvailability {
+    client
+        .get(format!(
+            "{}/user/by-handle/{}/availability",
+            address, handle
+        ))
+        .send()
+        .await
+        .expect("Failed to execute request.")
+        .json::<HandleAvailability>()
+        .await
+        .expect("Failed to get availability")
+}
+
+#[actix_web::test]
+#[serial_test::serial]
+async fn user_handles() -> Result<(), Box<dyn std::error::Error>> {
+    // Spawn App
+    let app = common::spawn_app().await;
+    // Do not follow redirects so old handle redirects can be checked
+    let client = Client::builder().redirect(Policy::none()).build()?;
+    let alice = common::create_user(&client, &app.address, "alice@gmail.com").await;
+    let bob = common::create_user(&client, &app.address, "bob@gmail.com").await;
+
+    // Claim a handle
+    assert!(availability(&client, &app.address, "alice").await.available);
+    assert_eq!(
+        set_handle(&client, &app.address, &alice, "alice@gmail.com", "Alice").await,
+        200
+    );
+
+    // Lookup is case-insensitive
+    let response = client
+        .get(format!("{}/user/by-handle/ALICE", &app.address))
+        .send()
+        .await?;
+    assert_eq!(response.status().as_u16(), 200);
+    let user = response.json::<GetUser>().await?;
+    assert_eq!(user.id, alice);
+    assert_eq!(user.handle.as_deref(), Some("Alice"));
+
+    // Handle is now unavailable, in any case
+    let taken = availability(&client, &app.address, "alice").await;
+    assert!(!taken.available);
+    assert_eq!(taken.reason, Some(HandleUnavailableReason::Taken));
+    assert_eq!(
+        set_handle(&client, &app.address, &bob, "bob@gmail.com", "aLiCe").await,
+        409
+    );
+
+    // Reserved words and invalid charset
+    let reserved = availability(&client, &app.address, "Admin").await;
+    assert_eq!(reserved.reason, Some(HandleUnavailableReason::Reserved));
+    let invalid = availability(&client, &app.address, "no-dashes").await;
+    assert_eq!(invalid.reason, Some(HandleUnavailableReason::Invalid));
+    assert_eq!(
+        set_handle(&client, &app.address, &bob, "bob@gmail.com", "admin").await,
+        400
+    );
+    assert_eq!(
+        set_handle(&client, &app.address, &bob, "bob@gmail.com", "a!").await,
+        400
+    );
+
+    // Changing handle redirects the old one
+    assert_eq!(
+        set_handle(
+            &client,
+            &app.address,
+            &alice,
+            "alice@gmail.com",
+            "alice_new"
+        )
+        .await,
+        200
+    );
+    let response = client
+        .get(format!("{}/user/by-handle/alice", &app.address))
+        .send()
+        .await?;
+    assert_eq!(response.status().as_u16(), 307);
+    assert_eq!(response.headers()["location"], "/user/by-handle/alice_new");
+
+    // Old handle stays with its owner during the grace period
+    assert!(!availability(&client, &app.address, "alice").await.available);
+    assert_eq!(
+        set_handle(&client, &app.address, &bob, "bob@gmail.com", "alice").await,
+        409
+    );
+    assert_eq!(
+        set_handle(&client, &app.address, &alice, "alice@gmail.com", "alice").await,
+        200
+    );
+
+    // Unknown handle
+    let response = client
+        .get(format!("{}/user/by-handle/nobody", &app.address))
+        .send()
+        .await?;
+    assert_eq!(response.status().as_u16(), 404);
+
+    Ok(())
+}
@@ -1,3 +1,6 @@
+// The original tests pass `&format!(..)` to reqwest, newer clippy flags the borrow
+#![allow(clippy::needless_borrows_for_generic_args)]
+
 mod common;
 
 #[actix_web::test]
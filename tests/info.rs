@@ -0,0 +1,32 @@
+use actix_template::routes::Info;
+
+mod common;
+
+#[actix_web::test]
+#[serial_test::serial]
+async fn info_describes_the_running_build() {
+    // Spawn App
+    let app = common::spawn_app().await;
+    let client = reqwest::Client::new();
+
+    let response = client
+        .get(format!("{}/info", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status().as_u16(), 200);
+    let info: Info = response.json().await.unwrap();
+    assert_eq!(info.version, env!("CARGO_PKG_VERSION"));
+    assert!(!info.git_commit.is_empty());
+    assert!(info.build_timestamp.is_some());
+    assert!(info.rustc_version.starts_with("rustc"));
+    assert_eq!(info.environment, "development");
+
+    // Every embedded migration is applied to the test database
+    let expected: Vec<i64> = sqlx::migrate!("./migrations")
+        .iter()
+        .filter(|migration| migration.migration_type.is_up_migration())
+        .map(|migration| migration.version)
+        .collect();
+    assert_eq!(info.migrations, expected);
+}
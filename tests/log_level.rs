@@ -0,0 +1,123 @@
+use actix_template::routes::LogLevel;
+use reqwest::Client;
+use serde_json::json;
+use std::collections::HashMap;
+
+mod common;
+
+async fn create_admin(app: &common::TestApp, client: &Client) {
+    let mut user_map = HashMap::new();
+    user_map.insert("name", "admin");
+    user_map.insert("email", "admin@gmail.com");
+    user_map.insert("password", "password");
+    user_map.insert("password_confirmation", "password");
+    let response = client
+        .post(format!("{}/user/", &app.address))
+        .json(&user_map)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status().as_u16(), 200);
+    sqlx::query("UPDATE users SET is_admin = true WHERE email = 'admin@gmail.com'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+}
+
+#[actix_web::test]
+#[serial_test::serial]
+async fn admins_can_change_the_log_level() {
+    // Spawn App
+    let app = common::spawn_app().await;
+    let client = Client::new();
+    create_admin(&app, &client).await;
+    let initial = app.log_filter.current();
+
+    let response = client
+        .put(format!("{}/admin/log-level", &app.admin_address))
+        .basic_auth("admin@gmail.com", Some("password"))
+        .json(&json!({ "directives": "warn,actix_template=trace", "revert_after_minutes": 5 }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status().as_u16(), 200);
+    let log_level: LogLevel = response.json().await.unwrap();
+    assert_eq!(log_level.previous, initial);
+    assert!(log_level.revert_at.is_some());
+    assert_eq!(
+        app.log_filter.current().as_deref(),
+        Some("actix_template=trace,warn")
+    );
+
+    // Restore for the other tests of this binary
+    app.log_filter.set(&initial.unwrap()).unwrap();
+}
+
+#[actix_web::test]
+#[serial_test::serial]
+async fn invalid_directives_are_rejected() {
+    // Spawn App
+    let app = common::spawn_app().await;
+    let client = Client::new();
+    create_admin(&app, &client).await;
+    let initial = app.log_filter.current();
+
+    let response = client
+        .put(format!("{}/admin/log-level", &app.admin_address))
+        .basic_auth("admin@gmail.com", Some("password"))
+        .json(&json!({ "directives": "actix_template=loud" }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(app.log_filter.current(), initial);
+}
+
+#[actix_web::test]
+#[serial_test::serial]
+async fn only_admins_can_change_the_log_level() {
+    // Spawn App
+    let app = common::spawn_app().await;
+    let client = Client::new();
+
+    let response = client
+        .put(format!("{}/admin/log-level", &app.admin_address))
+        .json(&json!({ "directives": "trace" }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[actix_web::test]
+#[serial_test::serial]
+async fn revert_does_not_undo_a_newer_change() {
+    // Spawn App
+    let app = common::spawn_app().await;
+    let initial = app.log_filter.current().unwrap();
+
+    let generation = app.log_filter.set("info").unwrap();
+    app.log_filter.set("debug").unwrap();
+    assert!(!app.log_filter.revert(&initial, generation).unwrap());
+    assert_eq!(app.log_filter.current().as_deref(), Some("debug"));
+
+    app.log_filter.set(&initial).unwrap();
+}
+
+#[actix_web::test]
+#[serial_test::serial]
+async fn log_level_is_not_served_on_the_public_port() {
+    // Spawn App
+    let app = common::spawn_app().await;
+    let client = Client::new();
+    create_admin(&app, &client).await;
+
+    let response = client
+        .put(format!("{}/admin/log-level", &app.address))
+        .basic_auth("admin@gmail.com", Some("password"))
+        .json(&json!({ "directives": "trace" }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status().as_u16(), 404);
+}
@@ -0,0 +1,136 @@
+mod common;
+
+#[actix_web::test]
+#[serial_test::serial]
+async fn metrics_are_served_on_the_admin_port_only() {
+    // Spawn App
+    let app = common::spawn_app().await;
+    let client = reqwest::Client::new();
+
+    let response = client
+        .get(format!("{}/metrics", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status().as_u16(), 404);
+
+    let response = client
+        .get(format!("{}/metrics", &app.admin_address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status().as_u16(), 200);
+    let body = response.text().await.unwrap();
+    assert!(body.contains("db_pool_connections"));
+    assert!(body.contains("db_pool_idle_connections"));
+    assert!(body.contains("db_pool_acquire_duration_seconds"));
+}
+
+#[actix_web::test]
+#[serial_test::serial]
+async fn metrics_record_requests_by_route_pattern_and_queries() {
+    // Spawn App
+    let app = common::spawn_app().await;
+    let client = reqwest::Client::new();
+
+    // Unknown user, labelled with the route pattern rather than the handle
+    let response = client
+        .get(format!("{}/user/by-handle/nobody_here", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status().as_u16(), 404);
+
+    let body = client
+        .get(format!("{}/metrics", &app.admin_address))
+        .send()
+        .await
+        .expect("Failed to execute request.")
+        .text()
+        .await
+        .unwrap();
+    assert!(body.contains(
+        r#"http_requests_total{method="GET",route="/user/by-handle/{handle}",status="404"}"#
+    ));
+    assert!(body.contains("http_request_duration_seconds_bucket"));
+    assert!(
+        body.contains(r#"db_query_duration_seconds_count{query="Get User By Handle In Database"}"#)
+    );
+}
+
+// Value of the sample named exactly `sample`, 0 before it is first recorded
+fn sample_value(body: &str, sample: &str) -> f64 {
+    body.lines()
+        .find_map(|line| line.strip_prefix(sample)?.strip_prefix(' '))
+        .map(|value| value.parse().unwrap())
+        .unwrap_or(0.0)
+}
+
+#[actix_web::test]
+#[serial_test::serial]
+async fn query_and_acquire_metrics_are_recorded_whatever_the_log_level() {
+    // Spawn App
+    let app = common::spawn_app().await;
+    let client = reqwest::Client::new();
+    let query = r#"db_query_duration_seconds_count{query="Get User By Handle In Database"}"#;
+    let acquire = "db_pool_acquire_duration_seconds_count";
+    let scrape = || async {
+        client
+            .get(format!("{}/metrics", &app.admin_address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+            .text()
+            .await
+            .unwrap()
+    };
+    let before = scrape().await;
+    let initial = app.log_filter.current().unwrap();
+    app.log_filter.set("warn").unwrap();
+
+    let response = client
+        .get(format!("{}/user/by-handle/nobody_here", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    app.log_filter.set(&initial).unwrap();
+    let after = scrape().await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 404);
+    assert!(sample_value(&after, query) > sample_value(&before, query));
+    // Recorded by the request, not by the scrapes
+    assert!(sample_value(&after, acquire) > sample_value(&before, acquire));
+}
+
+#[actix_web::test]
+#[serial_test::serial]
+async fn acquires_outside_repositories_are_timed_too() {
+    // Spawn App
+    let app = common::spawn_app().await;
+    let client = reqwest::Client::new();
+    let acquire = "db_pool_acquire_duration_seconds_count";
+    let scrape = || async {
+        client
+            .get(format!("{}/metrics", &app.admin_address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+            .text()
+            .await
+            .unwrap()
+    };
+    let before = scrape().await;
+
+    // The readiness checks query the database without a repository function
+    let response = client
+        .get(format!("{}/ready", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    let after = scrape().await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(sample_value(&after, acquire) >= sample_value(&before, acquire) + 2.0);
+}
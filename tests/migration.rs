@@ -0,0 +1,162 @@
+use actix_template::migration::{
+    applied_migrations, create_database_if_missing, schema_drift, MIGRATOR,
+};
+use actix_template::{get_configuration, Application, DatabaseSettings, Settings};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+mod common;
+use common::test_log_filter;
+
+// Settings for an empty database that migrates itself on startup
+async fn settings_for_empty_database() -> Settings {
+    let mut configuration = get_configuration().expect("Failed to read configuration.");
+    configuration.application.port = 0;
+    configuration.application.admin_port = 0;
+    configuration.database.database_name = Uuid::new_v4().to_string();
+    configuration.database.run_migrations_on_startup = true;
+    create_database_if_missing(&configuration.database)
+        .await
+        .expect("Failed to create database.");
+    configuration
+}
+
+// Versions of the up migrations embedded in the binary
+fn known_versions() -> Vec<i64> {
+    MIGRATOR
+        .iter()
+        .filter(|migration| migration.migration_type.is_up_migration())
+        .map(|migration| migration.version)
+        .collect()
+}
+
+// Tables in the public schema other than sqlx's bookkeeping
+async fn tables(db_pool: &PgPool) -> Vec<String> {
+    sqlx::query_scalar(
+        r#"
+        SELECT table_name::text FROM information_schema.tables
+        WHERE table_schema = 'public' AND table_name <> '_sqlx_migrations'
+        ORDER BY table_name
+        "#,
+    )
+    .fetch_all(db_pool)
+    .await
+    .unwrap()
+}
+
+// Settings and pool for an empty database, nothing applied yet
+async fn empty_database() -> (DatabaseSettings, PgPool) {
+    let mut configuration = get_configuration().expect("Failed to read configuration.");
+    configuration.database.database_name = Uuid::new_v4().to_string();
+    create_database_if_missing(&configuration.database)
+        .await
+        .expect("Failed to create database.");
+    let db_pool = PgPool::connect_with(configuration.database.with_database())
+        .await
+        .unwrap();
+    (configuration.database, db_pool)
+}
+
+#[actix_web::test]
+async fn every_migration_can_be_applied_reverted_and_applied_again() {
+    // Arrange
+    let (_, db_pool) = empty_database().await;
+
+    // Act
+    MIGRATOR.run(&db_pool).await.expect("Failed to apply.");
+    let migrated = tables(&db_pool).await;
+    MIGRATOR.undo(&db_pool, 0).await.expect("Failed to revert.");
+    let reverted = tables(&db_pool).await;
+    let applied_after_revert = applied_migrations(&db_pool).await.unwrap();
+    MIGRATOR
+        .run(&db_pool)
+        .await
+        .expect("Failed to apply again.");
+
+    // Assert
+    assert!(migrated.contains(&"audit_events".to_string()));
+    assert!(reverted.is_empty(), "{:?}", reverted);
+    assert!(applied_after_revert.is_empty());
+    assert_eq!(tables(&db_pool).await, migrated);
+    assert_eq!(
+        applied_migrations(&db_pool).await.unwrap(),
+        known_versions()
+    );
+}
+
+#[actix_web::test]
+async fn schema_drift_flags_columns_that_no_longer_match_the_query_data() {
+    // Arrange
+    let (configuration, db_pool) = empty_database().await;
+    MIGRATOR.run(&db_pool).await.expect("Failed to apply.");
+    let query_data = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join(".sqlx");
+    let before = schema_drift(&configuration, &query_data).await.unwrap();
+
+    // Act
+    sqlx::query("ALTER TABLE users ALTER COLUMN bio TYPE varchar(200)")
+        .execute(&db_pool)
+        .await
+        .unwrap();
+    let after = schema_drift(&configuration, &query_data).await.unwrap();
+
+    // Assert
+    assert!(before.is_empty(), "{:?}", before);
+    assert!(!after.is_empty());
+    // Selected as a column and bound as a parameter
+    assert!(after
+        .iter()
+        .any(|drift| drift.reason.contains("columns expected")));
+    assert!(after
+        .iter()
+        .any(|drift| drift.reason.contains("parameters expected")));
+    assert!(after.iter().any(|drift| drift.reason.contains("Varchar")));
+}
+
+#[actix_web::test]
+async fn replicas_starting_together_apply_migrations_once() {
+    // Arrange
+    let configuration = settings_for_empty_database().await;
+
+    // Act
+    let (first, second) = futures_util::join!(
+        Application::build(configuration.clone(), test_log_filter()),
+        Application::build(configuration.clone(), test_log_filter()),
+    );
+
+    // Assert
+    assert!(first.is_ok());
+    assert!(second.is_ok());
+    let db_pool = PgPool::connect_with(configuration.database.with_database())
+        .await
+        .unwrap();
+    let applied = applied_migrations(&db_pool).await.unwrap();
+    assert_eq!(applied, known_versions());
+}
+
+#[actix_web::test]
+async fn startup_is_refused_when_the_database_is_newer_than_the_binary() {
+    // Arrange
+    let configuration = settings_for_empty_database().await;
+    Application::build(configuration.clone(), test_log_filter())
+        .await
+        .expect("Failed to build application.");
+    let db_pool = PgPool::connect_with(configuration.database.with_database())
+        .await
+        .unwrap();
+    sqlx::query(
+        r#"
+        INSERT INTO _sqlx_migrations (version, description, success, checksum, execution_time)
+        VALUES (29991231000000, 'from the future', true, '\x00', 0)
+        "#,
+    )
+    .execute(&db_pool)
+    .await
+    .unwrap();
+
+    // Act
+    let result = Application::build(configuration, test_log_filter()).await;
+
+    // Assert
+    let error = result.err().expect("Build should fail.").to_string();
+    assert!(error.contains("29991231000000"), "{}", error);
+}
@@ -0,0 +1,75 @@
+use actix_template::telemetry::Redaction;
+use actix_template::{telemetry, LogFormat, OpenTelemetrySettings, OtlpProtocol};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+mod common;
+
+#[actix_web::test]
+async fn spans_continue_the_incoming_trace_and_are_exported() {
+    // In-process stand-in for an OTLP/HTTP collector
+    let collector = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/traces"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&collector)
+        .await;
+    let settings = OpenTelemetrySettings {
+        endpoint: collector.uri(),
+        protocol: OtlpProtocol::Http,
+    };
+    let tracer = telemetry::get_tracer(
+        "actix-template-test".into(),
+        &settings,
+        Redaction::default(),
+    )
+    .expect("Failed to build tracer.");
+    let (subscriber, log_filter) = telemetry::get_subscriber(
+        "actix-template-test".into(),
+        "info".into(),
+        LogFormat::Bunyan,
+        Redaction::default(),
+        std::io::sink,
+        Some(tracer),
+    );
+    telemetry::init_subscriber(subscriber);
+    let app = common::spawn_app_with_own_tracing(log_filter, |_| {}).await;
+    let client = reqwest::Client::new();
+
+    // Request carrying the gateway's trace context
+    let trace_id = "4bf92f3577b34da6a3ce929d0e0e4736";
+    let response = client
+        .get(format!("{}/user/by-handle/nobody_here", &app.address))
+        .header(
+            "traceparent",
+            format!("00-{}-00f067aa0ba902b7-01", trace_id),
+        )
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status().as_u16(), 404);
+
+    // Flush buffered spans to the collector
+    actix_web::rt::task::spawn_blocking(telemetry::shutdown_tracer)
+        .await
+        .unwrap();
+
+    // Protobuf keeps ids and names as raw bytes, look for them in the exported payloads
+    let exported: Vec<u8> = collector
+        .received_requests()
+        .await
+        .unwrap()
+        .into_iter()
+        .flat_map(|request| request.body)
+        .collect();
+    let trace_id_bytes: Vec<u8> = (0..trace_id.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&trace_id[i..i + 2], 16).unwrap())
+        .collect();
+    assert!(common::contains(&exported, &trace_id_bytes));
+    // The repository span is part of the same export
+    assert!(common::contains(
+        &exported,
+        b"Get User By Handle In Database"
+    ));
+}
@@ -0,0 +1,73 @@
+use std::collections::HashMap;
+
+use actix_template::telemetry::Redaction;
+use actix_template::{telemetry, LogFormat, OpenTelemetrySettings, OtlpProtocol};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+mod common;
+
+#[actix_web::test]
+async fn exported_spans_go_through_the_redaction_rules() {
+    // In-process stand-in for an OTLP/HTTP collector
+    let collector = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/traces"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&collector)
+        .await;
+    let settings = OpenTelemetrySettings {
+        endpoint: collector.uri(),
+        protocol: OtlpProtocol::Http,
+    };
+    let tracer = telemetry::get_tracer(
+        "actix-template-test".into(),
+        &settings,
+        Redaction::default(),
+    )
+    .expect("Failed to build tracer.");
+    let (subscriber, log_filter) = telemetry::get_subscriber(
+        "actix-template-test".into(),
+        "info".into(),
+        LogFormat::Bunyan,
+        Redaction::default(),
+        std::io::sink,
+        Some(tracer),
+    );
+    telemetry::init_subscriber(subscriber);
+    let app = common::spawn_app_with_own_tracing(log_filter, |_| {}).await;
+    let client = reqwest::Client::new();
+
+    // "Create User" records the email on its span
+    let email = "otlp.redaction@gmail.com";
+    let mut user_map = HashMap::new();
+    user_map.insert("name", "otlp");
+    user_map.insert("email", email);
+    user_map.insert("password", "password");
+    user_map.insert("password_confirmation", "password");
+    let response = client
+        .post(format!("{}/user/", &app.address))
+        .json(&user_map)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status().as_u16(), 200);
+
+    // Flush buffered spans to the collector
+    actix_web::rt::task::spawn_blocking(telemetry::shutdown_tracer)
+        .await
+        .unwrap();
+
+    let exported: Vec<u8> = collector
+        .received_requests()
+        .await
+        .unwrap()
+        .into_iter()
+        .flat_map(|request| request.body)
+        .collect();
+    assert!(common::contains(&exported, b"Create User"));
+    // The email is exported as its hash only
+    assert!(!common::contains(&exported, email.as_bytes()));
+    let hashed = Redaction::default().redact("email", email).unwrap();
+    assert!(common::contains(&exported, hashed.as_bytes()));
+}
@@ -0,0 +1,105 @@
+use actix_template::routes::{ComponentStatus, ReadinessReport};
+
+mod common;
+
+#[actix_web::test]
+#[serial_test::serial]
+async fn ready_reports_every_component() {
+    // Spawn App
+    let app = common::spawn_app().await;
+    let client = reqwest::Client::new();
+
+    let response = client
+        .get(format!("{}/ready", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status().as_u16(), 200);
+    let report: ReadinessReport = response.json().await.unwrap();
+    assert!(report.ready);
+    assert_eq!(report.components["database"].status, ComponentStatus::Up);
+    assert_eq!(report.components["migrations"].status, ComponentStatus::Up);
+}
+
+#[actix_web::test]
+#[serial_test::serial]
+async fn ready_fails_when_database_is_unreachable() {
+    // Point the app at a port nothing listens on
+    let app = common::spawn_app_with(|configuration| configuration.database.port = 1).await;
+    let client = reqwest::Client::new();
+
+    let response = client
+        .get(format!("{}/ready", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status().as_u16(), 503);
+    let report: ReadinessReport = response.json().await.unwrap();
+    assert!(!report.ready);
+    assert_eq!(report.components["database"].status, ComponentStatus::Down);
+
+    // Liveness does not depend on the database
+    let response = client
+        .get(format!("{}/health_check", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[actix_web::test]
+#[serial_test::serial]
+async fn ready_fails_with_pending_migrations() {
+    // Spawn App
+    let app = common::spawn_app().await;
+    let client = reqwest::Client::new();
+    // Forget the latest migration
+    sqlx::query(
+        "DELETE FROM _sqlx_migrations WHERE version = (SELECT MAX(version) FROM _sqlx_migrations)",
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+
+    let response = client
+        .get(format!("{}/ready", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status().as_u16(), 503);
+    let report: ReadinessReport = response.json().await.unwrap();
+    assert_eq!(
+        report.components["migrations"].status,
+        ComponentStatus::Down
+    );
+    assert_eq!(report.components["database"].status, ComponentStatus::Up);
+}
+
+#[actix_web::test]
+#[serial_test::serial]
+async fn ready_caches_results_briefly() {
+    // Spawn App
+    let app = common::spawn_app().await;
+    let client = reqwest::Client::new();
+
+    let response = client
+        .get(format!("{}/ready", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status().as_u16(), 200);
+
+    // A migration going missing right after a check is not noticed yet
+    sqlx::query(
+        "DELETE FROM _sqlx_migrations WHERE version = (SELECT MAX(version) FROM _sqlx_migrations)",
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+    let response = client
+        .get(format!("{}/ready", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status().as_u16(), 200);
+}
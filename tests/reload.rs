@@ -0,0 +1,124 @@
+use std::time::Duration;
+
+use actix_template::load_configuration_with_sources;
+use actix_template::reload::{watch, Reloader, SettingsHandle};
+
+mod common;
+use common::{copy_configuration_directory, test_log_filter, write_configuration_file};
+
+fn reloader(directory: &std::path::Path) -> (Reloader, SettingsHandle) {
+    let (settings, sources) = load_configuration_with_sources(directory, "development")
+        .expect("Failed to read configuration.");
+    let handle = SettingsHandle::new(settings);
+    let reloader = Reloader::new(
+        directory.to_path_buf(),
+        "development".into(),
+        sources,
+        handle.clone(),
+        test_log_filter(),
+    );
+    (reloader, handle)
+}
+
+#[actix_web::test]
+#[serial_test::serial]
+async fn reload_applies_reloadable_keys_and_rejects_the_rest() {
+    // Arrange
+    let directory = copy_configuration_directory();
+    let (mut reloader, settings) = reloader(&directory);
+    let port = settings.load().application.port;
+    write_configuration_file(
+        &directory,
+        "local.yaml",
+        "application:\n  port: 9999\n  base_url: \"https://reloaded.example.com\"\n",
+    );
+
+    // Act
+    let outcome = reloader.reload().expect("Reload failed.");
+
+    // Assert
+    assert_eq!(outcome.applied, ["application.base_url"]);
+    assert_eq!(outcome.rejected, ["application.port"]);
+    let settings = settings.load();
+    assert_eq!(
+        settings.application.base_url,
+        "https://reloaded.example.com"
+    );
+    assert_eq!(settings.application.port, port);
+}
+
+#[actix_web::test]
+#[serial_test::serial]
+async fn reload_only_reports_changes_since_the_last_reload() {
+    // Arrange
+    let directory = copy_configuration_directory();
+    let (mut reloader, settings) = reloader(&directory);
+    write_configuration_file(
+        &directory,
+        "local.yaml",
+        "application:\n  base_url: \"https://reloaded.example.com\"\n",
+    );
+    reloader.reload().expect("Reload failed.");
+    write_configuration_file(
+        &directory,
+        "local.yaml",
+        "application:\n  base_url: \"https://reloaded.example.com\"\nstorage:\n  max_avatar_size: 1024\n",
+    );
+
+    // Act
+    let outcome = reloader.reload().expect("Reload failed.");
+    let unchanged = reloader.reload().expect("Reload failed.");
+
+    // Assert
+    assert_eq!(outcome.applied, ["storage.max_avatar_size"]);
+    assert!(outcome.rejected.is_empty());
+    assert!(unchanged.applied.is_empty());
+    assert!(unchanged.rejected.is_empty());
+    assert_eq!(settings.load().storage.max_avatar_size, 1024);
+}
+
+#[actix_web::test]
+#[serial_test::serial]
+async fn invalid_configuration_keeps_the_current_settings() {
+    // Arrange
+    let directory = copy_configuration_directory();
+    let (mut reloader, settings) = reloader(&directory);
+    let max_avatar_size = settings.load().storage.max_avatar_size;
+    write_configuration_file(
+        &directory,
+        "local.yaml",
+        "storage:\n  max_avatar_size: 1024\napplication:\n  base_url: \"not a url\"\n",
+    );
+
+    // Act
+    let result = reloader.reload();
+
+    // Assert
+    assert!(result.is_err());
+    assert_eq!(settings.load().storage.max_avatar_size, max_avatar_size);
+}
+
+#[actix_web::test]
+#[serial_test::serial]
+async fn file_changes_are_picked_up_by_the_watcher() {
+    // Arrange
+    let directory = copy_configuration_directory();
+    let (reloader, settings) = reloader(&directory);
+    watch(reloader).expect("Failed to watch configuration.");
+
+    // Act
+    write_configuration_file(
+        &directory,
+        "local.yaml",
+        "storage:\n  max_avatar_size: 1024\n",
+    );
+
+    // Assert
+    for _ in 0..50 {
+        if settings.load().storage.max_avatar_size == 1024 {
+            return;
+        }
+        actix_web::rt::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("Configuration was not reloaded.");
+}
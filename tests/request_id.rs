@@ -0,0 +1,148 @@
+use reqwest::Client;
+use serde_json::json;
+use uuid::Uuid;
+use wiremock::matchers::{header, method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+mod common;
+
+#[actix_web::test]
+#[serial_test::serial]
+async fn request_id_is_generated_and_echoed() {
+    // Spawn App
+    let app = common::spawn_app().await;
+    let client = Client::new();
+
+    let response = client
+        .get(format!("{}/health_check", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status().as_u16(), 200);
+    let request_id = response.headers()["x-request-id"].to_str().unwrap();
+    assert!(Uuid::parse_str(request_id).is_ok());
+}
+
+#[actix_web::test]
+#[serial_test::serial]
+async fn valid_incoming_request_id_is_kept() {
+    // Spawn App
+    let app = common::spawn_app().await;
+    let client = Client::new();
+
+    let response = client
+        .get(format!("{}/health_check", &app.address))
+        .header("X-Request-Id", "gateway-1234.abc_DEF")
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.headers()["x-request-id"], "gateway-1234.abc_DEF");
+}
+
+#[actix_web::test]
+#[serial_test::serial]
+async fn invalid_incoming_request_id_is_replaced() {
+    // Spawn App
+    let app = common::spawn_app().await;
+    let client = Client::new();
+
+    for invalid in ["has spaces", "semi;colon", &"a".repeat(129)] {
+        let response = client
+            .get(format!("{}/health_check", &app.address))
+            .header("X-Request-Id", invalid)
+            .send()
+            .await
+            .expect("Failed to execute request.");
+        let request_id = response.headers()["x-request-id"].to_str().unwrap();
+        assert_ne!(request_id, invalid);
+        assert!(Uuid::parse_str(request_id).is_ok());
+    }
+}
+
+#[actix_web::test]
+#[serial_test::serial]
+async fn error_bodies_include_the_request_id() {
+    // Spawn App
+    let app = common::spawn_app().await;
+    let client = Client::new();
+
+    // Plain text error
+    let response = client
+        .get(format!("{}/user/by-handle/nobody_here", &app.address))
+        .header("X-Request-Id", "req-404")
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status().as_u16(), 404);
+    assert_eq!(response.headers()["x-request-id"], "req-404");
+    assert_eq!(
+        response.text().await.unwrap(),
+        "User not found (request id: req-404)"
+    );
+
+    // JSON error
+    let id: Uuid = client
+        .post(format!("{}/user/", &app.address))
+        .json(&json!({
+            "name": "request",
+            "email": "request@gmail.com",
+            "password": "password",
+            "password_confirmation": "password"
+        }))
+        .send()
+        .await
+        .expect("Failed to execute request.")
+        .json()
+        .await
+        .unwrap();
+    let response = client
+        .put(format!("{}/user/{}", &app.address, id))
+        .basic_auth("request@gmail.com", Some("password"))
+        .header("X-Request-Id", "req-400")
+        .json(&json!({ "locale": "not-a-locale" }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status().as_u16(), 400);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["request_id"], "req-400");
+}
+
+#[actix_web::test]
+#[serial_test::serial]
+async fn request_id_is_forwarded_to_the_email_api() {
+    // Spawn App
+    let app = common::spawn_app().await;
+    let client = Client::new();
+    Mock::given(method("POST"))
+        .and(path("/email"))
+        .and(header("X-Request-Id", "req-email"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let id: Uuid = client
+        .post(format!("{}/user/", &app.address))
+        .json(&json!({
+            "name": "request",
+            "email": "request@gmail.com",
+            "password": "password",
+            "password_confirmation": "password"
+        }))
+        .send()
+        .await
+        .expect("Failed to execute request.")
+        .json()
+        .await
+        .unwrap();
+    let response = client
+        .post(format!("{}/user/{}/email", &app.address, id))
+        .basic_auth("request@gmail.com", Some("password"))
+        .header("X-Request-Id", "req-email")
+        .json(&json!({ "email": "new-request@gmail.com" }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status().as_u16(), 202);
+}
@@ -0,0 +1,82 @@
+use reqwest::Client;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+mod common;
+
+#[actix_web::test]
+async fn shutdown_flips_readiness_and_runs_hooks() {
+    // Spawn App
+    let app = common::spawn_app().await;
+    // Idle keep-alive connections would hold the drain until they time out
+    let client = Client::builder().pool_max_idle_per_host(0).build().unwrap();
+
+    // Ready while running
+    let response = client
+        .get(format!("{}/ready", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status().as_u16(), 200);
+
+    // Register a hook standing in for a background worker
+    let worker_stopped = Arc::new(AtomicBool::new(false));
+    let hook_flag = worker_stopped.clone();
+    app.server_handle
+        .register_shutdown_hook("stop worker", move || async move {
+            hook_flag.store(true, Ordering::SeqCst);
+        });
+
+    app.server_handle.shutdown().await;
+
+    assert!(!app.server_handle.readiness().is_ready());
+    assert!(worker_stopped.load(Ordering::SeqCst));
+    // No longer accepting connections
+    let response = client
+        .get(format!("{}/health_check", &app.address))
+        .send()
+        .await;
+    assert!(response.is_err());
+
+    // Shutting down twice is harmless
+    app.server_handle.shutdown().await;
+}
+
+#[actix_web::test]
+async fn ready_reports_not_ready_before_draining_starts() {
+    // Spawn App
+    let app = common::spawn_app_with(|configuration| {
+        configuration
+            .application
+            .shutdown_readiness_delay_milliseconds = 1000;
+    })
+    .await;
+    let client = Client::builder().pool_max_idle_per_host(0).build().unwrap();
+
+    let server_handle = app.server_handle.clone();
+    let shutdown = actix_web::rt::spawn(async move { server_handle.shutdown().await });
+    actix_web::rt::time::sleep(std::time::Duration::from_millis(200)).await;
+
+    // Still serving during the delay, but load balancers are told to stop routing
+    let response = client
+        .get(format!("{}/ready", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status().as_u16(), 503);
+    let response = client
+        .get(format!("{}/health_check", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status().as_u16(), 200);
+
+    shutdown.await.unwrap();
+    let response = client
+        .get(format!("{}/health_check", &app.address))
+        .send()
+        .await;
+    assert!(response.is_err());
+}
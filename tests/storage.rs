@@ -0,0 +1,80 @@
+use actix_template::storage::{BlobStore, S3BlobStore};
+use actix_template::S3Settings;
+use actix_web::{web, App, HttpResponse, HttpServer};
+use secrecy::Secret;
+use std::collections::HashMap;
+use std::net::TcpListener;
+use std::sync::Mutex;
+
+type Objects = web::Data<Mutex<HashMap<String, web::Bytes>>>;
+
+// Minimal stand-in for an S3 compatible server such as MinIO
+fn spawn_s3_stand_in() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("Failed to bind random port");
+    let address = format!("http://{}", listener.local_addr().unwrap());
+    let objects: Objects = web::Data::new(Mutex::new(HashMap::new()));
+    let server = HttpServer::new(move || {
+        App::new().app_data(objects.clone()).route(
+            "/{path:.*}",
+            web::route().to(
+                |request: actix_web::HttpRequest, body: web::Bytes, objects: Objects| async move {
+                    let path = request.path().to_string();
+                    let mut objects = objects.lock().unwrap();
+                    match *request.method() {
+                        actix_web::http::Method::PUT => {
+                            objects.insert(path, body);
+                            HttpResponse::Ok().finish()
+                        }
+                        actix_web::http::Method::GET => match objects.get(&path) {
+                            Some(object) => HttpResponse::Ok().body(object.clone()),
+                            None => HttpResponse::NotFound().finish(),
+                        },
+                        actix_web::http::Method::DELETE => {
+                            objects.remove(&path);
+                            HttpResponse::NoContent().finish()
+                        }
+                        _ => HttpResponse::MethodNotAllowed().finish(),
+                    }
+                },
+            ),
+        )
+    })
+    .listen(listener)
+    .expect("Failed to listen")
+    .run();
+    actix::spawn(server);
+    address
+}
+
+#[actix_web::test]
+async fn s3_blob_store_round_trip() -> Result<(), Box<dyn std::error::Error>> {
+    let endpoint = spawn_s3_stand_in();
+    let store = S3BlobStore::new(&S3Settings {
+        bucket: "avatars".into(),
+        region: "local".into(),
+        endpoint,
+        access_key: "access".into(),
+        secret_key: Secret::new("secret".into()),
+        path_style: true,
+    })?;
+
+    store
+        .put(
+            "avatars/1/original",
+            web::Bytes::from_static(b"blob"),
+            "image/png",
+        )
+        .await?;
+    let blob = store.get("avatars/1/original").await?;
+    assert_eq!(blob.as_deref(), Some(b"blob".as_ref()));
+
+    store.delete("avatars/1/original").await?;
+    assert!(store.get("avatars/1/original").await?.is_none());
+
+    // Keys cannot escape the bucket prefix
+    assert!(store
+        .put("../secret", web::Bytes::from_static(b"blob"), "image/png")
+        .await
+        .is_err());
+    Ok(())
+}
@@ -0,0 +1,154 @@
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+use actix_template::telemetry::{get_subscriber, rotating_file, Redaction};
+use actix_template::{get_configuration, LogFileSettings, LogFormat, LogRotation};
+use uuid::Uuid;
+
+// Sink collecting everything written by the subscriber
+#[derive(Clone, Default)]
+struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Buffer {
+    fn contents(&self) -> String {
+        String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+    }
+}
+
+fn log_with(format: LogFormat, filter: &str) -> String {
+    let buffer = Buffer::default();
+    let sink = buffer.clone();
+    let (subscriber, _) = get_subscriber(
+        "actix-template-test".into(),
+        filter.into(),
+        format,
+        Redaction::default(),
+        move || sink.clone(),
+        None,
+    );
+    tracing::subscriber::with_default(subscriber, || {
+        tracing::info!(answer = 42, "Hello from the test");
+        tracing::debug!("Filtered out");
+        tracing::info!(
+            email = "jane@example.com",
+            new_email = "jane@example.org",
+            recipient = "jane@example.net",
+            password = "hunter2-hunter2",
+            token = "abcdefghijklmnopqrstuvwxyz123456",
+            http.target = "/user/email/confirm?token=abcdefghijklmnopqrstuvwxyz123456",
+            "Sensitive"
+        );
+    });
+    buffer.contents()
+}
+
+#[test]
+fn bunyan_format_writes_json_lines() {
+    let output = log_with(LogFormat::Bunyan, "info");
+    let line: serde_json::Value = serde_json::from_str(output.lines().next().unwrap()).unwrap();
+    assert_eq!(line["msg"], "Hello from the test");
+    assert_eq!(line["answer"], 42);
+    assert!(!output.contains("Filtered out"));
+}
+
+#[test]
+fn compact_format_writes_one_plain_line_per_event() {
+    let output = log_with(LogFormat::Compact, "info");
+    assert_eq!(output.lines().count(), 2);
+    assert!(output.contains("Hello from the test"));
+    assert!(output.contains("answer=42"));
+    assert!(serde_json::from_str::<serde_json::Value>(&output).is_err());
+}
+
+#[test]
+fn bunyan_format_redacts_sensitive_fields() {
+    let output = log_with(LogFormat::Bunyan, "info");
+    let line: serde_json::Value = serde_json::from_str(output.lines().nth(1).unwrap()).unwrap();
+    assert_eq!(line["msg"], "Sensitive");
+    assert!(line["email"].as_str().unwrap().starts_with("sha256:"));
+    assert!(line["new_email"].as_str().unwrap().starts_with("sha256:"));
+    assert!(line["recipient"].as_str().unwrap().starts_with("sha256:"));
+    assert!(line.get("password").is_none());
+    assert_eq!(line["token"], "****3456");
+    assert_eq!(line["http.target"], "/user/email/confirm?token=****3456");
+    assert!(!output.contains("jane@example"));
+    assert!(!output.contains("hunter2"));
+    assert!(!output.contains("abcdefgh"));
+}
+
+#[test]
+fn compact_format_redacts_sensitive_fields() {
+    let output = log_with(LogFormat::Compact, "info");
+    assert!(output.contains("email=sha256:"));
+    assert!(output.contains("recipient=sha256:"));
+    assert!(output.contains("token=****3456"));
+    assert!(!output.contains("password"));
+    assert!(!output.contains("jane@example"));
+    assert!(!output.contains("abcdefgh"));
+}
+
+#[test]
+fn same_value_hashes_the_same() {
+    let redaction = Redaction::default();
+    assert_eq!(
+        redaction.redact("email", "jane@example.com"),
+        redaction.redact("old_email", "jane@example.com")
+    );
+    assert_ne!(
+        redaction.redact("email", "jane@example.com"),
+        redaction.redact("email", "john@example.com")
+    );
+}
+
+#[test]
+fn secrets_never_render_in_settings() {
+    let configuration = get_configuration().expect("Failed to read configuration.");
+    let rendered = format!("{:?}", configuration);
+    assert!(!rendered.contains("my-secret-token"));
+    assert!(rendered.contains("REDACTED"));
+}
+
+#[test]
+fn filter_directives_are_applied() {
+    let output = log_with(LogFormat::Compact, "debug");
+    assert!(output.contains("Filtered out"));
+}
+
+#[test]
+fn size_rotation_keeps_only_the_newest_files() {
+    let directory = std::env::temp_dir()
+        .join("actix-template-test")
+        .join(Uuid::new_v4().to_string());
+    let settings = LogFileSettings {
+        directory: directory.to_string_lossy().into(),
+        file_name: "app.log".into(),
+        rotation: LogRotation::Size,
+        max_size_bytes: 16,
+        max_files: 2,
+    };
+    let mut file = rotating_file(&settings).unwrap();
+    for i in 0..10 {
+        writeln!(file, "line number {:02}", i).unwrap();
+    }
+    file.flush().unwrap();
+
+    let mut names: Vec<String> = std::fs::read_dir(&directory)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into())
+        .collect();
+    names.sort();
+    // Current file plus the retained rotations
+    assert_eq!(names, vec!["app.log", "app.log.1", "app.log.2"]);
+    let newest_rotated = std::fs::read_to_string(directory.join("app.log.1")).unwrap();
+    assert!(newest_rotated.contains("line number 0"));
+}
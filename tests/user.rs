@@ -1,5 +1,10 @@
+// The original tests pass `&format!(..)` to reqwest, newer clippy flags the borrow
+#![allow(clippy::needless_borrows_for_generic_args)]
+
+use actix_template::authentication::verify_password;
 use actix_template::routes::GetUser;
 use reqwest::{self, Client};
+use secrecy::Secret;
 use std::collections::HashMap;
 use uuid::Uuid;
 
@@ -89,9 +94,10 @@ async fn user_crud() -> Result<(), Box<dyn std::error::Error>> {
     // Update user
     let mut user_map = HashMap::new();
     user_map.insert("name", "test2");
-    user_map.insert("password", "test2");
+    user_map.insert("password", "password2");
     let response = client
         .put(&format!("{}/user/{}", &app.address, &id))
+        .basic_auth("test@gmail.com", Some("test"))
         .json(&user_map)
         .send()
         .await
@@ -103,11 +109,13 @@ async fn user_crud() -> Result<(), Box<dyn std::error::Error>> {
         .fetch_one(&app.db_pool)
         .await?;
     assert_eq!(user.name, "test2");
-    assert_eq!(user.password, "test2");
+    // Only a hash of the new password is stored
+    assert!(verify_password(user.password, Secret::new("password2".into())).await);
 
     // Delete user
     let response = client
         .delete(&format!("{}/user/{}", &app.address, &id))
+        .basic_auth("test@gmail.com", Some("password2"))
         .send()
         .await
         .expect("Failed to execute request.");
@@ -121,3 +129,191 @@ async fn user_crud() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+#[actix_web::test]
+#[serial_test::serial]
+async fn user_profile() -> Result<(), Box<dyn std::error::Error>> {
+    // Spawn App
+    let app = common::spawn_app().await;
+    // Create Client
+    let client = Client::new();
+
+    // User
+    let mut user_map = HashMap::new();
+    user_map.insert("name", "profile");
+    user_map.insert("email", "profile@gmail.com");
+    user_map.insert("password", "password");
+    user_map.insert("password_confirmation", "password");
+    let response = client
+        .post(format!("{}/user/", &app.address))
+        .json(&user_map)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status().as_u16(), 200);
+    let id = response.json::<Uuid>().await?;
+
+    // Update profile
+    let mut profile_map = HashMap::new();
+    profile_map.insert("display_name", "Profile User");
+    profile_map.insert("locale", "en-GB");
+    profile_map.insert("timezone", "Europe/London");
+    profile_map.insert("bio", "Hello");
+    profile_map.insert("avatar_url", "https://example.com/avatar.png");
+    let response = client
+        .put(format!("{}/user/{}", &app.address, &id))
+        .basic_auth("profile@gmail.com", Some("password"))
+        .json(&profile_map)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status().as_u16(), 200);
+
+    // Profile is exposed through GetUser
+    let user = client
+        .get(format!("{}/user/{}", &app.address, &id))
+        .send()
+        .await
+        .expect("Failed to execute request.")
+        .json::<GetUser>()
+        .await?;
+    assert_eq!(user.name, "profile");
+    assert_eq!(user.display_name.as_deref(), Some("Profile User"));
+    assert_eq!(user.locale.as_deref(), Some("en-GB"));
+    assert_eq!(user.timezone.as_deref(), Some("Europe/London"));
+    assert_eq!(user.bio.as_deref(), Some("Hello"));
+    assert_eq!(
+        user.avatar_url.as_deref(),
+        Some("https://example.com/avatar.png")
+    );
+
+    // Unknown locale and timezone are rejected
+    for (field, value) in [("locale", "xx-YY"), ("timezone", "Mars/Olympus_Mons")] {
+        let mut invalid_map = HashMap::new();
+        invalid_map.insert(field, value);
+        let response = client
+            .put(format!("{}/user/{}", &app.address, &id))
+            .basic_auth("profile@gmail.com", Some("password"))
+            .json(&invalid_map)
+            .send()
+            .await
+            .expect("Failed to execute request.");
+        assert_eq!(response.status().as_u16(), 400);
+    }
+
+    Ok(())
+}
+
+#[actix_web::test]
+#[serial_test::serial]
+async fn users_can_only_change_their_own_account() -> Result<(), Box<dyn std::error::Error>> {
+    // Spawn App
+    let app = common::spawn_app().await;
+    let client = Client::new();
+    let mut ids = Vec::new();
+    for email in ["owner@gmail.com", "other@gmail.com"] {
+        let mut user_map = HashMap::new();
+        user_map.insert("name", "owner");
+        user_map.insert("email", email);
+        user_map.insert("password", "password");
+        user_map.insert("password_confirmation", "password");
+        let response = client
+            .post(format!("{}/user/", &app.address))
+            .json(&user_map)
+            .send()
+            .await?;
+        ids.push(response.json::<Uuid>().await?);
+    }
+    let owner = ids[0];
+    let mut user_map = HashMap::new();
+    user_map.insert("password", "taken-over");
+
+    // Anonymous
+    let response = client
+        .put(format!("{}/user/{}", &app.address, &owner))
+        .json(&user_map)
+        .send()
+        .await?;
+    assert_eq!(response.status().as_u16(), 401);
+    let response = client
+        .delete(format!("{}/user/{}", &app.address, &owner))
+        .send()
+        .await?;
+    assert_eq!(response.status().as_u16(), 401);
+
+    // Another user
+    let response = client
+        .put(format!("{}/user/{}", &app.address, &owner))
+        .basic_auth("other@gmail.com", Some("password"))
+        .json(&user_map)
+        .send()
+        .await?;
+    assert_eq!(response.status().as_u16(), 403);
+    let response = client
+        .delete(format!("{}/user/{}", &app.address, &owner))
+        .basic_auth("other@gmail.com", Some("password"))
+        .send()
+        .await?;
+    assert_eq!(response.status().as_u16(), 403);
+
+    // The owner still signs in with the original password
+    let response = client
+        .delete(format!("{}/user/{}", &app.address, &owner))
+        .basic_auth("owner@gmail.com", Some("password"))
+        .send()
+        .await?;
+    assert_eq!(response.status().as_u16(), 200);
+
+    Ok(())
+}
+
+#[actix_web::test]
+#[serial_test::serial]
+async fn plaintext_passwords_are_hashed_on_sign_in() -> Result<(), Box<dyn std::error::Error>> {
+    // Spawn App
+    let app = common::spawn_app().await;
+    let client = Client::new();
+    // Row written before passwords were hashed
+    let id = Uuid::new_v4();
+    sqlx::query!(
+        r#"INSERT INTO users (id, name, email, password) VALUES ($1, 'legacy', 'legacy@gmail.com', 'password')"#,
+        &id
+    )
+    .execute(&app.db_pool)
+    .await?;
+    let mut user_map = HashMap::new();
+    user_map.insert("name", "legacy2");
+
+    // Wrong password
+    let response = client
+        .put(format!("{}/user/{}", &app.address, &id))
+        .basic_auth("legacy@gmail.com", Some("wrong"))
+        .json(&user_map)
+        .send()
+        .await?;
+    assert_eq!(response.status().as_u16(), 401);
+
+    // Signs in and the password is hashed in place
+    let response = client
+        .put(format!("{}/user/{}", &app.address, &id))
+        .basic_auth("legacy@gmail.com", Some("password"))
+        .json(&user_map)
+        .send()
+        .await?;
+    assert_eq!(response.status().as_u16(), 200);
+    let user = sqlx::query!(r#"SELECT password FROM users WHERE id = $1"#, &id)
+        .fetch_one(&app.db_pool)
+        .await?;
+    assert!(verify_password(user.password, Secret::new("password".into())).await);
+
+    // And keeps signing in with the same password
+    let response = client
+        .put(format!("{}/user/{}", &app.address, &id))
+        .basic_auth("legacy@gmail.com", Some("password"))
+        .json(&user_map)
+        .send()
+        .await?;
+    assert_eq!(response.status().as_u16(), 200);
+
+    Ok(())
+}